@@ -0,0 +1,1026 @@
+use clap::ValueEnum;
+use color_eyre::{
+    eyre::{eyre, Context, ContextCompat},
+    Result,
+};
+use reqwest::{header::HeaderMap, Client, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tiktoken_rs::{
+    get_chat_completion_max_tokens, get_completion_max_tokens, ChatCompletionRequestMessage,
+};
+use url::{Host, Url};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub prompt_tokens: usize,
+    #[serde(default)]
+    pub completion_tokens: usize,
+    #[serde(default)]
+    pub total_tokens: usize,
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishReason {
+    Stop,
+    Length,
+    ContentFilter,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug)]
+pub struct Completion {
+    pub text: String,
+    pub usage: Usage,
+    pub finish_reason: Option<FinishReason>,
+    pub rate_limits: RateLimits,
+}
+
+/// Rate limit state reported by the server in `x-ratelimit-*` headers.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RateLimits {
+    pub limit_requests: Option<u32>,
+    pub limit_tokens: Option<u32>,
+    pub remaining_requests: Option<u32>,
+    pub remaining_tokens: Option<u32>,
+    pub reset_requests: Option<Duration>,
+    pub reset_tokens: Option<Duration>,
+}
+
+/// Parses durations like `20ms`, `1s` or `6m0.5s`.
+fn parse_reset(text: &str) -> Option<Duration> {
+    let mut total = 0.;
+    let mut rest = text.trim();
+
+    while !rest.is_empty() {
+        let number_end = rest.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let value = rest[..number_end].parse::<f64>().ok()?;
+        rest = &rest[number_end..];
+        let unit_end = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+
+        total += value
+            * match &rest[..unit_end] {
+                "ms" => 0.001,
+                "s" => 1.,
+                "m" => 60.,
+                "h" => 3600.,
+                _ => return None,
+            };
+
+        rest = &rest[unit_end..];
+    }
+
+    Some(Duration::from_secs_f64(total))
+}
+
+impl RateLimits {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: &str| headers.get(format!("x-ratelimit-{}", name))?.to_str().ok();
+        let number = |name| header(name)?.trim().parse().ok();
+        let duration = |name| parse_reset(header(name)?);
+
+        Self {
+            limit_requests: number("limit-requests"),
+            limit_tokens: number("limit-tokens"),
+            remaining_requests: number("remaining-requests"),
+            remaining_tokens: number("remaining-tokens"),
+            reset_requests: duration("reset-requests"),
+            reset_tokens: duration("reset-tokens"),
+        }
+    }
+}
+
+impl Completion {
+    /// Whether the model stopped because it ran out of tokens.
+    pub fn is_truncated(&self) -> bool {
+        self.finish_reason == Some(FinishReason::Length)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+impl Role {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::User => "user",
+            Self::Assistant => "assistant",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+}
+
+impl Message {
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+        }
+    }
+}
+
+const CONTINUE: &str = "Continue exactly where you left off, without repeating anything.";
+
+/// A conversation made of system instructions, few-shot example pairs and the
+/// actual request, always ending with a user message.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub messages: Vec<Message>,
+    /// Truncated answer to continue
+    pub partial: Option<String>,
+}
+
+impl Prompt {
+    pub fn new(system: impl Into<String>) -> Self {
+        Self {
+            messages: vec![Message::new(Role::System, system)],
+            partial: None,
+        }
+    }
+
+    pub fn example(mut self, user: impl Into<String>, assistant: impl Into<String>) -> Self {
+        self.messages.push(Message::new(Role::User, user));
+        self.messages.push(Message::new(Role::Assistant, assistant));
+        self
+    }
+
+    pub fn user(mut self, content: impl Into<String>) -> Self {
+        self.messages.push(Message::new(Role::User, content));
+        self
+    }
+
+    pub fn continuation(&self, partial: impl Into<String>) -> Self {
+        Self {
+            messages: self.messages.clone(),
+            partial: Some(partial.into()),
+        }
+    }
+
+    /// The conversation, with a truncated answer followed by a request to
+    /// continue it.
+    pub fn chat_messages(&self) -> Vec<Message> {
+        let mut messages = self.messages.clone();
+
+        if let Some(partial) = &self.partial {
+            messages.push(Message::new(Role::Assistant, partial));
+            messages.push(Message::new(Role::User, CONTINUE));
+        }
+
+        messages
+    }
+
+    /// Flattens the conversation for the legacy completions endpoint, where a
+    /// truncated answer is simply continued.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        for message in &self.messages {
+            match message.role {
+                Role::System => text.push_str(&format!("#{}\n", message.content)),
+                Role::User => text.push_str(&format!("{}\n\n", message.content)),
+                Role::Assistant => text.push_str(&format!("PHP:\n{}\n\n", message.content)),
+            }
+        }
+
+        text.push_str("PHP:");
+
+        if let Some(partial) = &self.partial {
+            text.push_str(partial);
+        }
+
+        text
+    }
+
+    fn to_tiktoken(&self) -> Vec<ChatCompletionRequestMessage> {
+        self.chat_messages()
+            .iter()
+            .map(|message| ChatCompletionRequestMessage {
+                role: message.role.as_str().to_owned(),
+                content: message.content.clone(),
+                name: None,
+            })
+            .collect()
+    }
+}
+
+pub trait Backend: Send + Sync {
+    fn kind(&self) -> BackendKind;
+    /// Endpoint the completions are requested from
+    fn url(&self) -> &str;
+    fn model(&self) -> &str;
+    fn complete<'a>(&'a self, prompt: &'a Prompt) -> BoxFuture<'a, Result<Completion>>;
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    /// OpenAI legacy completions endpoint
+    Completions,
+    /// OpenAI chat completions endpoint
+    Chat,
+    /// Any server exposing an OpenAI-compatible chat completions endpoint
+    Compatible,
+}
+
+impl BackendKind {
+    fn default_model(self) -> &'static str {
+        match self {
+            Self::Completions => "text-davinci-003",
+            Self::Chat | Self::Compatible => "gpt-3.5-turbo",
+        }
+    }
+
+    pub fn build(
+        self,
+        client: Client,
+        base_url: Option<&str>,
+        model: Option<String>,
+    ) -> Result<Arc<dyn Backend>> {
+        let base_url = match (self, base_url) {
+            (Self::Compatible, None) => {
+                return Err(eyre!("--base-url is required for this backend"));
+            }
+            (_, base_url) => base_url.unwrap_or(OPENAI_BASE_URL).trim_end_matches('/'),
+        };
+
+        let model = model.unwrap_or_else(|| self.default_model().to_owned());
+
+        let backend: Arc<dyn Backend> = match self {
+            Self::Completions => Arc::new(OpenAiCompletions {
+                client,
+                url: format!("{}/completions", base_url),
+                model,
+            }),
+            Self::Chat => Arc::new(OpenAiChat {
+                client,
+                url: format!("{}/chat/completions", base_url),
+                model,
+            }),
+            Self::Compatible => Arc::new(Compatible {
+                client,
+                url: format!("{}/chat/completions", base_url),
+                model,
+            }),
+        };
+
+        Ok(backend)
+    }
+}
+
+/// Whether requests to `base_url` stay on this machine or the local network,
+/// in which case no API key is needed.
+pub fn is_local(base_url: &str) -> Result<bool> {
+    let url = Url::parse(base_url).wrap_err_with(|| format!("{}: Invalid URL", base_url))?;
+
+    let local = match url.host() {
+        Some(Host::Domain(domain)) => {
+            domain == "localhost" || domain.ends_with(".localhost") || !domain.contains('.')
+        }
+        Some(Host::Ipv4(ip)) => ip.is_loopback() || ip.is_private() || ip.is_link_local(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    };
+
+    Ok(local)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Response<T> {
+    Ok {
+        choices: Vec<T>,
+        #[serde(default)]
+        usage: Usage,
+    },
+    Err {
+        error: Error,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct Error {
+    message: String,
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+    #[serde(default)]
+    code: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    RateLimited,
+    Timeout,
+    Overloaded,
+    Server,
+    Network,
+    Authentication,
+    QuotaExceeded,
+    ContextLengthExceeded,
+    InvalidRequest,
+    Other,
+}
+
+/// A failed API request, classified to tell transient failures from fatal ones.
+#[derive(Debug)]
+pub struct ApiError {
+    pub kind: ErrorKind,
+    pub status: Option<StatusCode>,
+    pub message: String,
+    pub retry_after: Option<Duration>,
+    pub rate_limits: RateLimits,
+}
+
+impl ApiError {
+    fn new(
+        status: StatusCode,
+        error: Option<&Error>,
+        retry_after: Option<Duration>,
+        rate_limits: RateLimits,
+    ) -> Self {
+        let code = match error.and_then(|e| e.code.as_ref()) {
+            Some(serde_json::Value::String(code)) => code.as_str(),
+            _ => "",
+        };
+
+        let kind = error.and_then(|e| e.kind.as_deref()).unwrap_or_default();
+
+        let kind = match (status.as_u16(), code, kind) {
+            (_, "context_length_exceeded", _) => ErrorKind::ContextLengthExceeded,
+            (_, "insufficient_quota", _) | (_, _, "insufficient_quota") => ErrorKind::QuotaExceeded,
+            (401 | 403, _, _) | (_, "invalid_api_key", _) => ErrorKind::Authentication,
+            (429, _, _) => ErrorKind::RateLimited,
+            (408 | 504, _, _) => ErrorKind::Timeout,
+            (503 | 529, _, _) => ErrorKind::Overloaded,
+            (_, _, kind) if kind.contains("overloaded") => ErrorKind::Overloaded,
+            (500..=599, _, _) | (_, _, "server_error") => ErrorKind::Server,
+            (400..=499, _, _) => ErrorKind::InvalidRequest,
+            _ => ErrorKind::Other,
+        };
+
+        Self {
+            kind,
+            status: Some(status),
+            message: error.map_or_else(
+                || status.canonical_reason().unwrap_or_default().to_owned(),
+                |e| e.message.clone(),
+            ),
+            retry_after,
+            rate_limits,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self.kind,
+            ErrorKind::RateLimited
+                | ErrorKind::Timeout
+                | ErrorKind::Overloaded
+                | ErrorKind::Server
+                | ErrorKind::Network
+        )
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        Self {
+            kind: match e.is_timeout() {
+                true => ErrorKind::Timeout,
+                false => ErrorKind::Network,
+            },
+            status: e.status(),
+            message: e.to_string(),
+            retry_after: None,
+            rate_limits: RateLimits::default(),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "{}: {}", status, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// The delay requested by the server, in milliseconds, seconds or until an
+/// HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name| Some(headers.get(name)?.to_str().ok()?.trim());
+    let number = |name| header(name)?.parse::<f64>().ok();
+
+    let seconds = number("retry-after-ms")
+        .map(|ms| ms / 1000.)
+        .or_else(|| number("retry-after"))
+        .filter(|secs| secs.is_finite() && *secs >= 0.)
+        .map(Duration::from_secs_f64);
+
+    seconds.or_else(|| {
+        let date = httpdate::parse_http_date(header("retry-after")?).ok()?;
+        Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+    })
+}
+
+async fn post<T: DeserializeOwned>(
+    client: &Client,
+    url: &str,
+    request: &impl Serialize,
+) -> Result<(T, Usage, RateLimits)> {
+    let response = client
+        .post(url)
+        .json(request)
+        .send()
+        .await
+        .map_err(ApiError::from)?;
+
+    let status = response.status();
+    let retry_after = retry_after(response.headers());
+    let rate_limits = RateLimits::from_headers(response.headers());
+    let body = response.text().await.map_err(ApiError::from)?;
+
+    match (
+        status.is_success(),
+        serde_json::from_str::<Response<T>>(&body),
+    ) {
+        (_, Ok(Response::Err { error })) => {
+            Err(ApiError::new(status, Some(&error), retry_after, rate_limits).into())
+        }
+        (true, Ok(Response::Ok { choices, usage })) => {
+            let choice = choices.into_iter().next().wrap_err("No choice received")?;
+            Ok((choice, usage, rate_limits))
+        }
+        (false, _) => Err(ApiError::new(status, None, retry_after, rate_limits).into()),
+        (true, Err(e)) => Err(eyre!(e).wrap_err("Invalid response")),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CompletionRequest<'a> {
+    model: &'a str,
+    prompt: &'a str,
+    max_tokens: usize,
+    temperature: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionChoice {
+    text: String,
+    finish_reason: Option<FinishReason>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: &'a [Message],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<usize>,
+    temperature: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+    finish_reason: Option<FinishReason>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    #[serde(default)]
+    content: String,
+}
+
+pub struct OpenAiCompletions {
+    client: Client,
+    url: String,
+    model: String,
+}
+
+impl Backend for OpenAiCompletions {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Completions
+    }
+
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete<'a>(&'a self, prompt: &'a Prompt) -> BoxFuture<'a, Result<Completion>> {
+        Box::pin(async move {
+            let prompt = prompt.to_text();
+            let max_tokens =
+                get_completion_max_tokens(&self.model, &prompt).map_err(|e| eyre!(e))?;
+
+            let request = CompletionRequest {
+                model: &self.model,
+                prompt: &prompt,
+                max_tokens,
+                temperature: 0.,
+            };
+
+            let (choice, usage, rate_limits) =
+                post::<CompletionChoice>(&self.client, &self.url, &request).await?;
+
+            Ok(Completion {
+                text: choice.text,
+                usage,
+                finish_reason: choice.finish_reason,
+                rate_limits,
+            })
+        })
+    }
+}
+
+async fn chat(
+    client: &Client,
+    url: &str,
+    model: &str,
+    prompt: &Prompt,
+    max_tokens: Option<usize>,
+) -> Result<Completion> {
+    let messages = prompt.chat_messages();
+
+    let request = ChatRequest {
+        model,
+        messages: &messages,
+        max_tokens,
+        temperature: 0.,
+    };
+
+    let (choice, usage, rate_limits) = post::<ChatChoice>(client, url, &request).await?;
+
+    Ok(Completion {
+        text: choice.message.content,
+        usage,
+        finish_reason: choice.finish_reason,
+        rate_limits,
+    })
+}
+
+pub struct OpenAiChat {
+    client: Client,
+    url: String,
+    model: String,
+}
+
+impl Backend for OpenAiChat {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Chat
+    }
+
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete<'a>(&'a self, prompt: &'a Prompt) -> BoxFuture<'a, Result<Completion>> {
+        Box::pin(async move {
+            // Models unknown to tiktoken are left to the server's default length
+            let max_tokens =
+                get_chat_completion_max_tokens(&self.model, &prompt.to_tiktoken()).ok();
+
+            chat(&self.client, &self.url, &self.model, prompt, max_tokens).await
+        })
+    }
+}
+
+/// Unlike the OpenAI backends, the model name is opaque here, so no local token
+/// accounting is done and the server picks the completion length.
+pub struct Compatible {
+    client: Client,
+    url: String,
+    model: String,
+}
+
+impl Backend for Compatible {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Compatible
+    }
+
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete<'a>(&'a self, prompt: &'a Prompt) -> BoxFuture<'a, Result<Completion>> {
+        Box::pin(chat(&self.client, &self.url, &self.model, prompt, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        sync::mpsc::{self, Receiver},
+        thread,
+    };
+
+    /// Answers a single request, returning the base URL and the request line
+    /// and body it receives.
+    fn serve(status: &'static str, body: &'static str) -> (String, Receiver<(String, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+
+            let (head, length) = loop {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request).into_owned();
+
+                if let Some((head, _)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            line.to_ascii_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|n| n.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+
+                    break (head.to_owned(), length);
+                }
+            };
+
+            while request.len() < head.len() + 4 + length {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+
+            let line = head.lines().next().unwrap_or_default().to_owned();
+            let request_body = String::from_utf8_lossy(&request[head.len() + 4..]).into_owned();
+            let _ = sender.send((line, request_body));
+
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\
+                connection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+
+        (base_url, receiver)
+    }
+
+    const CHAT_RESPONSE: &str = r#"{
+        "choices": [{"message": {"role": "assistant", "content": "<?php"}, "finish_reason": "stop"}],
+        "usage": {"prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7}
+    }"#;
+
+    #[tokio::test]
+    async fn compatible_server() {
+        let (base_url, requests) = serve("200 OK", CHAT_RESPONSE);
+        let backend = BackendKind::Compatible
+            .build(Client::new(), Some(&base_url), Some("local".to_owned()))
+            .unwrap();
+
+        let completion = backend
+            .complete(&Prompt::new("Convert").user("class A {}"))
+            .await
+            .unwrap();
+
+        assert_eq!(completion.text, "<?php");
+        assert_eq!(completion.usage.total_tokens, 7);
+
+        let (line, body) = requests.recv().unwrap();
+        let request = serde_json::from_str::<serde_json::Value>(&body).unwrap();
+        assert_eq!(line, "POST /v1/chat/completions HTTP/1.1");
+        assert_eq!(request["model"], "local");
+        assert_eq!(request.get("max_tokens"), None);
+
+        let (base_url, _) = serve(
+            "400 Bad Request",
+            r#"{"error": {"message": "Unknown model", "type": "invalid_request_error"}}"#,
+        );
+
+        let backend = BackendKind::Compatible
+            .build(Client::new(), Some(&base_url), None)
+            .unwrap();
+
+        let error = backend
+            .complete(&Prompt::new("Convert").user("class A {}"))
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "400 Bad Request: Unknown model");
+    }
+
+    #[test]
+    fn usage_adds_up() {
+        let mut usage = Usage::default();
+
+        for _ in 0..2 {
+            usage += Usage {
+                prompt_tokens: 5,
+                completion_tokens: 2,
+                total_tokens: 7,
+            };
+        }
+
+        assert_eq!(
+            (
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                usage.total_tokens
+            ),
+            (10, 4, 14)
+        );
+    }
+
+    #[test]
+    fn chat_prompts() {
+        let prompt = Prompt::new("Convert")
+            .example("Java:\nclass A {}", "<?php\nclass A {}")
+            .user("Java:\nclass B {}");
+
+        let messages = serde_json::to_value(prompt.chat_messages()).unwrap();
+
+        assert_eq!(
+            messages,
+            serde_json::json!([
+                {"role": "system", "content": "Convert"},
+                {"role": "user", "content": "Java:\nclass A {}"},
+                {"role": "assistant", "content": "<?php\nclass A {}"},
+                {"role": "user", "content": "Java:\nclass B {}"},
+            ])
+        );
+
+        assert_eq!(
+            prompt.to_text(),
+            "#Convert\nJava:\nclass A {}\n\nPHP:\n<?php\nclass A {}\n\nJava:\nclass B {}\n\nPHP:"
+        );
+    }
+
+    #[tokio::test]
+    async fn base_urls() {
+        for (base_url, local) in [
+            ("http://localhost:11434/v1", true),
+            ("http://127.0.0.1:8080", true),
+            ("http://192.168.1.20/v1", true),
+            ("http://[::1]:8080", true),
+            ("http://ollama:11434", true),
+            ("https://api.openai.com/v1", false),
+            ("http://8.8.8.8", false),
+        ] {
+            assert_eq!(is_local(base_url).unwrap(), local, "{}", base_url);
+        }
+
+        assert!(is_local("localhost").is_err());
+
+        let error = BackendKind::Compatible
+            .build(Client::new(), None, None)
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "--base-url is required for this backend");
+
+        // Trailing slashes are left out, and the default model of the backend used
+        let (base_url, requests) = serve("200 OK", CHAT_RESPONSE);
+        let backend = BackendKind::Chat
+            .build(Client::new(), Some(&format!("{}/", base_url)), None)
+            .unwrap();
+
+        backend
+            .complete(&Prompt::new("Convert").user("class A {}"))
+            .await
+            .unwrap();
+
+        let (line, body) = requests.recv().unwrap();
+        let request = serde_json::from_str::<serde_json::Value>(&body).unwrap();
+        assert_eq!(line, "POST /v1/chat/completions HTTP/1.1");
+        assert_eq!(request["model"], "gpt-3.5-turbo");
+    }
+
+    #[test]
+    fn continuations() {
+        let prompt = Prompt::new("Convert")
+            .user("Java:\nclass A {}")
+            .continuation("<?php\nclass A");
+
+        let messages = prompt.chat_messages();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[2].role, Role::Assistant);
+        assert_eq!(messages[2].content, "<?php\nclass A");
+        assert_eq!(messages[3].content, CONTINUE);
+        assert!(prompt
+            .to_text()
+            .ends_with("Java:\nclass A {}\n\nPHP:<?php\nclass A"));
+
+        let reasons = serde_json::from_str::<Vec<Option<FinishReason>>>(
+            r#"["stop", "length", "content_filter", "tool_calls", null]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            reasons,
+            [
+                Some(FinishReason::Stop),
+                Some(FinishReason::Length),
+                Some(FinishReason::ContentFilter),
+                Some(FinishReason::Other),
+                None,
+            ]
+        );
+
+        let completion = |finish_reason| Completion {
+            text: String::new(),
+            usage: Usage::default(),
+            finish_reason,
+            rate_limits: RateLimits::default(),
+        };
+
+        assert!(completion(Some(FinishReason::Length)).is_truncated());
+        assert!(!completion(Some(FinishReason::Stop)).is_truncated());
+        assert!(!completion(None).is_truncated());
+    }
+
+    fn headers(headers: &[(&'static str, &str)]) -> HeaderMap {
+        headers
+            .iter()
+            .map(|&(name, value)| (name, HeaderValue::from_str(value).unwrap()))
+            .map(|(name, value)| (reqwest::header::HeaderName::from_static(name), value))
+            .collect()
+    }
+
+    #[test]
+    fn retry_after_formats() {
+        assert_eq!(
+            retry_after(&headers(&[("retry-after-ms", "1500")])),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            retry_after(&headers(&[("retry-after", "20")])),
+            Some(Duration::from_secs(20))
+        );
+
+        // Milliseconds are more precise
+        assert_eq!(
+            retry_after(&headers(&[("retry-after", "1"), ("retry-after-ms", "250")])),
+            Some(Duration::from_millis(250))
+        );
+
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(120));
+        let delay = retry_after(&headers(&[("retry-after", &date)])).unwrap();
+        assert!(delay > Duration::from_secs(110) && delay <= Duration::from_secs(120));
+
+        // Dates in the past mean now
+        assert_eq!(
+            retry_after(&headers(&[(
+                "retry-after",
+                "Wed, 21 Oct 2015 07:28:00 GMT"
+            )])),
+            Some(Duration::ZERO)
+        );
+
+        assert_eq!(retry_after(&headers(&[("retry-after", "-1")])), None);
+        assert_eq!(retry_after(&headers(&[("retry-after", "soon")])), None);
+        assert_eq!(retry_after(&headers(&[])), None);
+    }
+
+    #[test]
+    fn reset_durations() {
+        assert_eq!(parse_reset("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_reset("6m0.5s"), Some(Duration::from_millis(360_500)));
+        assert_eq!(parse_reset("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_reset("5 days"), None);
+    }
+
+    fn error(status: u16, kind: Option<&str>, code: Option<&str>) -> ApiError {
+        let error = kind.or(code).map(|_| Error {
+            message: "message".to_owned(),
+            kind: kind.map(str::to_owned),
+            code: code.map(|code| serde_json::json!(code)),
+        });
+
+        ApiError::new(
+            StatusCode::from_u16(status).unwrap(),
+            error.as_ref(),
+            None,
+            RateLimits::default(),
+        )
+    }
+
+    #[test]
+    fn classification() {
+        for (status, kind, code, expected, retryable) in [
+            (429, None, None, ErrorKind::RateLimited, true),
+            (408, None, None, ErrorKind::Timeout, true),
+            (504, None, None, ErrorKind::Timeout, true),
+            (503, None, None, ErrorKind::Overloaded, true),
+            (529, None, None, ErrorKind::Overloaded, true),
+            (
+                200,
+                Some("overloaded_error"),
+                None,
+                ErrorKind::Overloaded,
+                true,
+            ),
+            (500, None, None, ErrorKind::Server, true),
+            (200, Some("server_error"), None, ErrorKind::Server, true),
+            (401, None, None, ErrorKind::Authentication, false),
+            (403, None, None, ErrorKind::Authentication, false),
+            (
+                400,
+                None,
+                Some("invalid_api_key"),
+                ErrorKind::Authentication,
+                false,
+            ),
+            (
+                429,
+                Some("insufficient_quota"),
+                None,
+                ErrorKind::QuotaExceeded,
+                false,
+            ),
+            (
+                429,
+                None,
+                Some("insufficient_quota"),
+                ErrorKind::QuotaExceeded,
+                false,
+            ),
+            (
+                400,
+                Some("invalid_request_error"),
+                Some("context_length_exceeded"),
+                ErrorKind::ContextLengthExceeded,
+                false,
+            ),
+            (404, None, None, ErrorKind::InvalidRequest, false),
+            (302, None, None, ErrorKind::Other, false),
+        ] {
+            let error = error(status, kind, code);
+
+            assert_eq!(error.kind, expected, "{} {:?} {:?}", status, kind, code);
+            assert_eq!(error.is_retryable(), retryable, "{:?}", expected);
+        }
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!(
+            error(429, Some("requests"), None).to_string(),
+            "429 Too Many Requests: message"
+        );
+        assert_eq!(
+            error(503, None, None).to_string(),
+            "503 Service Unavailable: Service Unavailable"
+        );
+    }
+}
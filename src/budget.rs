@@ -0,0 +1,114 @@
+use crate::{
+    backend::{Backend, BackendKind, BoxFuture, Completion, Prompt, Usage},
+    pricing::Price,
+};
+use color_eyre::Result;
+use std::sync::{Arc, Mutex};
+
+/// Limits on what a whole run may spend, checked against the usage the API
+/// actually reports.
+pub struct Budget {
+    max_tokens: Option<usize>,
+    /// Maximum cost in USD and the price it is computed with
+    max_cost: Option<(f64, Price)>,
+    used: Mutex<Usage>,
+}
+
+impl Budget {
+    pub fn new(max_tokens: Option<usize>, max_cost: Option<(f64, Price)>) -> Self {
+        Self {
+            max_tokens,
+            max_cost,
+            used: Mutex::default(),
+        }
+    }
+
+    pub fn record(&self, usage: Usage) {
+        *self.used.lock().unwrap() += usage;
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        let used = *self.used.lock().unwrap();
+
+        self.max_tokens.is_some_and(|max| used.total_tokens >= max)
+            || self.max_cost.is_some_and(|(max, price)| {
+                price.cost(used.prompt_tokens, used.completion_tokens) >= max
+            })
+    }
+}
+
+/// Records the usage of every completion in a budget.
+pub struct Metered {
+    pub inner: Arc<dyn Backend>,
+    pub budget: Arc<Budget>,
+}
+
+impl Backend for Metered {
+    fn kind(&self) -> BackendKind {
+        self.inner.kind()
+    }
+
+    fn url(&self) -> &str {
+        self.inner.url()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn complete<'a>(&'a self, prompt: &'a Prompt) -> BoxFuture<'a, Result<Completion>> {
+        Box::pin(async move {
+            let completion = self.inner.complete(prompt).await?;
+            self.budget.record(completion.usage);
+            Ok(completion)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt_tokens: usize, completion_tokens: usize) -> Usage {
+        Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+
+    #[test]
+    fn tokens() {
+        let budget = Budget::new(Some(1000), None);
+        assert!(!budget.is_exhausted());
+
+        budget.record(usage(400, 200));
+        assert!(!budget.is_exhausted());
+
+        budget.record(usage(300, 100));
+        assert!(budget.is_exhausted());
+    }
+
+    #[test]
+    fn cost() {
+        let price = Price {
+            prompt: 0.01,
+            completion: 0.03,
+        };
+
+        // $0.04 for a thousand tokens each
+        let budget = Budget::new(None, Some((0.1, price)));
+        budget.record(usage(1000, 1000));
+        assert!(!budget.is_exhausted());
+
+        budget.record(usage(1000, 1000));
+        assert!(!budget.is_exhausted());
+
+        budget.record(usage(0, 1000));
+        assert!(budget.is_exhausted());
+
+        let unlimited = Budget::new(None, None);
+        unlimited.record(usage(1_000_000, 1_000_000));
+        assert!(!unlimited.is_exhausted());
+    }
+}
@@ -0,0 +1,87 @@
+use crate::sha256::Sha256;
+use color_eyre::{eyre::Context, Result};
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// Converted PHP files stored by a hash of everything the conversion depends on.
+pub struct Cache {
+    pub dir: PathBuf,
+}
+
+impl Cache {
+    /// Combines the parts of a key unambiguously, whatever they contain.
+    pub fn key<'a>(parts: impl IntoIterator<Item = &'a str>) -> String {
+        let mut hasher = Sha256::default();
+
+        for part in parts {
+            hasher
+                .update((part.len() as u64).to_be_bytes())
+                .update(part);
+        }
+
+        hasher.hex_digest()
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(&key[..2]).join(format!("{}.php", &key[2..]))
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        let path = self.path(key);
+
+        match fs::read_to_string(&path) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).wrap_err_with(|| format!("{}", path.display())),
+        }
+    }
+
+    pub fn put(&self, key: &str, content: &str) -> Result<()> {
+        let path = self.path(key);
+        let dir = path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir).wrap_err_with(|| format!("{}", dir.display()))?;
+
+        // Written aside and renamed so concurrent runs never read a partial entry
+        let partial = path.with_extension("php.partial");
+        fs::write(&partial, content)?;
+        fs::rename(&partial, &path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        let key = Cache::key(["a", "bc"]);
+
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, Cache::key(["a", "bc"]));
+        assert_ne!(key, Cache::key(["ab", "c"]));
+        assert_ne!(key, Cache::key(["abc"]));
+    }
+
+    #[test]
+    fn get_and_put() {
+        let cache = Cache {
+            dir: std::env::temp_dir().join(format!("java-to-php-cache-{}", std::process::id())),
+        };
+
+        let key = Cache::key(["class A {}"]);
+        assert_eq!(cache.get(&key).unwrap(), None);
+
+        cache.put(&key, "<?php\nclass A {}\n").unwrap();
+        let cached = cache.get(&key).unwrap();
+        let path = cache.path(&key);
+        fs::remove_dir_all(&cache.dir).unwrap();
+
+        assert_eq!(cached.as_deref(), Some("<?php\nclass A {}\n"));
+        assert!(path.starts_with(cache.dir.join(&key[..2])));
+    }
+}
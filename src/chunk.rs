@@ -0,0 +1,161 @@
+use crate::java::{JavaFile, MemberKind, TypeDecl};
+use std::ops::Range;
+
+/// How a Java file too large for a single request is converted piecewise.
+#[derive(Debug)]
+pub struct Plan {
+    /// Name of the type whose methods are split off
+    pub type_name: String,
+    /// The whole file with the method bodies of that type elided, sent as context
+    pub skeleton: String,
+    /// The whole file without the methods of that type, converted first
+    pub shell: String,
+    /// Source of each method of that type, leading comments included
+    pub methods: Vec<String>,
+}
+
+fn method_spans(
+    decl: &TypeDecl,
+) -> impl Iterator<Item = (Range<usize>, Option<Range<usize>>)> + '_ {
+    decl.members.iter().filter_map(|member| match &member.kind {
+        MemberKind::Method(method) | MemberKind::Constructor(method) => {
+            Some((member.span.clone(), method.body.clone()))
+        }
+        _ => None,
+    })
+}
+
+/// Replaces the given ranges, which must be sorted and disjoint.
+fn splice(
+    source: &str,
+    replacements: impl IntoIterator<Item = (Range<usize>, &'static str)>,
+) -> String {
+    let mut result = String::with_capacity(source.len());
+    let mut last = 0;
+
+    for (range, replacement) in replacements {
+        result.push_str(&source[last..range.start]);
+        result.push_str(replacement);
+        last = range.end;
+    }
+
+    result.push_str(&source[last..]);
+    result
+}
+
+/// Splits off the methods of the file's largest type.
+pub fn plan(source: &str, file: &JavaFile) -> Option<Plan> {
+    let decl = file
+        .types
+        .iter()
+        .filter(|decl| method_spans(decl).next().is_some())
+        .max_by_key(|decl| decl.span.len())?;
+
+    Some(Plan {
+        type_name: decl.name.clone(),
+        skeleton: splice(
+            source,
+            method_spans(decl).filter_map(|(_, body)| Some((body?, "{ ... }"))),
+        ),
+        shell: splice(source, method_spans(decl).map(|(span, _)| (span, ""))),
+        methods: method_spans(decl)
+            .map(|(span, _)| source[span].to_owned())
+            .collect(),
+    })
+}
+
+impl Plan {
+    /// Groups consecutive methods into chunks of at most `budget` tokens each,
+    /// except for single methods that are larger on their own.
+    pub fn chunks(&self, budget: usize, count: impl Fn(&str) -> usize) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut chunk = String::new();
+        let mut chunk_tokens = 0;
+
+        for method in &self.methods {
+            let tokens = count(method);
+
+            if !chunk.is_empty() && chunk_tokens + tokens > budget {
+                chunks.push(std::mem::take(&mut chunk));
+                chunk_tokens = 0;
+            }
+
+            chunk.push_str(method);
+            chunk_tokens += tokens;
+        }
+
+        chunks.push(chunk);
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::java;
+
+    const SOURCE: &str = "package a;
+
+class Small {
+    void f() {}
+}
+
+class Invoice {
+    private int total;
+
+    /** Adds an amount. */
+    void add(int amount) {
+        total += amount;
+    }
+
+    Invoice() {}
+
+    int total() {
+        return total;
+    }
+}
+";
+
+    #[test]
+    fn largest_type() {
+        let plan = plan(SOURCE, &java::parse(SOURCE).unwrap()).unwrap();
+
+        assert_eq!(plan.type_name, "Invoice");
+        assert_eq!(
+            plan.methods,
+            [
+                "\n\n    /** Adds an amount. */\n    void add(int amount) {\n        total += amount;\n    }",
+                "\n\n    Invoice() {}",
+                "\n\n    int total() {\n        return total;\n    }",
+            ]
+        );
+        assert_eq!(
+            plan.skeleton,
+            SOURCE
+                .replace("{\n        total += amount;\n    }", "{ ... }")
+                .replace("{}\n\n    int", "{ ... }\n\n    int")
+                .replace("{\n        return total;\n    }", "{ ... }")
+        );
+        assert_eq!(
+            plan.shell,
+            "package a;\n\nclass Small {\n    void f() {}\n}\n\nclass Invoice {\n    \
+            private int total;\n}\n"
+        );
+
+        let interface = "interface A { int X = 1; }";
+        assert!(super::plan(interface, &java::parse(interface).unwrap()).is_none());
+    }
+
+    #[test]
+    fn chunks() {
+        let plan = Plan {
+            type_name: "A".to_owned(),
+            skeleton: String::new(),
+            shell: String::new(),
+            methods: ["a", "bb", "cccccc", "d"].map(str::to_owned).to_vec(),
+        };
+
+        assert_eq!(plan.chunks(4, str::len), ["abb", "cccccc", "d"]);
+        assert_eq!(plan.chunks(100, str::len), ["abbccccccd"]);
+    }
+}
@@ -0,0 +1,324 @@
+use crate::{
+    config::Override,
+    php::{self, TokenKind, Version},
+};
+use color_eyre::{eyre::Context, Result};
+use serde::Serialize;
+use serde_json::{json, ser::PrettyFormatter, Map, Value};
+use std::{fs, io::ErrorKind, path::Path};
+
+pub struct Library {
+    /// Namespace prefix of the classes it provides
+    pub namespace: &'static str,
+    pub package: &'static str,
+    pub version: &'static str,
+    /// Only needed to run the tests
+    pub dev: bool,
+}
+
+const fn library(namespace: &'static str, package: &'static str, version: &'static str) -> Library {
+    Library {
+        namespace,
+        package,
+        version,
+        dev: false,
+    }
+}
+
+/// A package to add to `require` or `require-dev`.
+pub struct Requirement {
+    pub package: String,
+    pub version: String,
+    pub dev: bool,
+}
+
+impl From<&Library> for Requirement {
+    fn from(library: &Library) -> Self {
+        Self {
+            package: library.package.to_owned(),
+            version: library.version.to_owned(),
+            dev: library.dev,
+        }
+    }
+}
+
+/// Libraries converted code commonly relies on for what Java has built in or
+/// commonly uses.
+pub const LIBRARIES: &[Library] = &[
+    library("Brick\\Math", "brick/math", "^0.11"),
+    library("Carbon", "nesbot/carbon", "^2.67"),
+    library(
+        "Doctrine\\Common\\Collections",
+        "doctrine/collections",
+        "^2.1",
+    ),
+    library("GuzzleHttp", "guzzlehttp/guzzle", "^7.7"),
+    library("Monolog", "monolog/monolog", "^3.4"),
+    library("Psr\\Container", "psr/container", "^2.0"),
+    library("Psr\\Log", "psr/log", "^3.0"),
+    library("Ramsey\\Uuid", "ramsey/uuid", "^4.7"),
+    library(
+        "Symfony\\Component\\Serializer",
+        "symfony/serializer",
+        "^6.3",
+    ),
+    Library {
+        namespace: "PHPUnit",
+        package: "phpunit/phpunit",
+        version: "^10.2",
+        dev: true,
+    },
+];
+
+/// Libraries whose classes some PHP code refers to by qualified name.
+pub fn libraries(code: &str) -> Result<Vec<&'static Library>> {
+    let tokens = php::lex(code)?;
+    let mut names = Vec::<String>::new();
+    let mut end = 0;
+
+    for token in tokens {
+        if !matches!(token.kind, TokenKind::Ident | TokenKind::Punct('\\')) {
+            continue;
+        }
+
+        let text = &code[token.start..token.end];
+
+        // Parts of a name are never separated by whitespace
+        match names.last_mut() {
+            Some(name) if token.start == end => name.push_str(text),
+            _ => names.push(text.to_owned()),
+        }
+
+        end = token.end;
+    }
+
+    Ok(LIBRARIES
+        .iter()
+        .filter(|library| {
+            names.iter().any(|name| {
+                name.trim_start_matches('\\')
+                    .strip_prefix(library.namespace)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('\\'))
+            })
+        })
+        .collect())
+}
+
+/// PSR-4 autoload entries for the top-level namespaces of the converted files,
+/// which are placed under `src/` at the path of their namespace.
+pub fn autoload<'a>(namespaces: impl IntoIterator<Item = Option<&'a str>>) -> Map<String, Value> {
+    namespaces
+        .into_iter()
+        .map(
+            |namespace| match namespace.and_then(|n| n.split('\\').next()) {
+                Some(root) => (format!("{}\\", root), json!(format!("src/{}/", root))),
+                None => (String::new(), json!("src/")),
+            },
+        )
+        .collect()
+}
+
+/// The PHP version the package requires, the highest of those the files are
+/// converted for, since code converted for it does not run on older ones.
+pub fn php_version<'a>(
+    php_version: Version,
+    overrides: impl IntoIterator<Item = &'a Override>,
+) -> Version {
+    overrides
+        .into_iter()
+        .filter_map(|settings_override| settings_override.php_version)
+        .chain([php_version])
+        .max()
+        .unwrap_or(php_version)
+}
+
+/// The object under `key`, replacing whatever else is there.
+fn section<'a>(composer: &'a mut Map<String, Value>, key: &str) -> &'a mut Map<String, Value> {
+    let value = composer.entry(key).or_insert_with(|| json!({}));
+
+    if !value.is_object() {
+        *value = json!({});
+    }
+
+    value.as_object_mut().unwrap()
+}
+
+/// Writes the autoload entries, the PHP version and the requirements into
+/// `composer.json`, keeping whatever else an existing one contains, including
+/// chosen versions of the packages.
+pub fn write(
+    path: impl AsRef<Path>,
+    autoload: Map<String, Value>,
+    requirements: &[Requirement],
+    php_version: Version,
+) -> Result<()> {
+    let path = path.as_ref();
+
+    let mut composer = match fs::read_to_string(path) {
+        Ok(content) => {
+            serde_json::from_str(&content).wrap_err_with(|| format!("{}", path.display()))?
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => Map::new(),
+        Err(e) => return Err(e).wrap_err_with(|| format!("{}", path.display())),
+    };
+
+    section(&mut composer, "autoload").insert("psr-4".to_owned(), Value::Object(autoload));
+
+    // Converted code only runs on the version it was converted for
+    section(&mut composer, "require").insert("php".to_owned(), json!(format!(">={}", php_version)));
+
+    for requirement in requirements {
+        let key = match requirement.dev {
+            true => "require-dev",
+            false => "require",
+        };
+
+        section(&mut composer, key)
+            .entry(&requirement.package)
+            .or_insert_with(|| json!(requirement.version));
+    }
+
+    let mut content = Vec::new();
+    let formatter = PrettyFormatter::with_indent(b"    ");
+    composer.serialize(&mut serde_json::Serializer::with_formatter(
+        &mut content,
+        formatter,
+    ))?;
+    content.push(b'\n');
+
+    fs::write(path, content).wrap_err_with(|| format!("{}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn autoload_entries() {
+        let autoload = autoload([
+            Some("App\\Models"),
+            Some("App"),
+            Some("Acme\\Billing"),
+            None,
+        ]);
+
+        assert_eq!(
+            Value::Object(autoload),
+            json!({"App\\": "src/App/", "Acme\\": "src/Acme/", "": "src/"})
+        );
+    }
+
+    #[test]
+    fn used_libraries() {
+        let code = "<?php\n\nnamespace App;\n\nuse Brick\\Math\\BigDecimal;\n\n\
+            class A extends \\PHPUnit\\Framework\\TestCase\n{\n    \
+            // Carbon is only mentioned\n    private $logs = Logger::class;\n}\n";
+
+        let packages = libraries(code)
+            .unwrap()
+            .iter()
+            .map(|library| library.package)
+            .collect::<Vec<_>>();
+
+        assert_eq!(packages, ["brick/math", "phpunit/phpunit"]);
+        assert!(libraries("<?php\nuse Carbonara\\Pasta;")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn write_new() {
+        let dir =
+            std::env::temp_dir().join(format!("java-to-php-composer-new-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("composer.json");
+
+        let requirements = LIBRARIES
+            .iter()
+            .filter(|library| ["ramsey/uuid", "phpunit/phpunit"].contains(&library.package))
+            .map(Requirement::from)
+            .collect::<Vec<_>>();
+
+        write(&path, autoload([Some("App")]), &requirements, Version::V8_1).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            content,
+            r#"{
+    "autoload": {
+        "psr-4": {
+            "App\\": "src/App/"
+        }
+    },
+    "require": {
+        "php": ">=8.1",
+        "ramsey/uuid": "^4.7"
+    },
+    "require-dev": {
+        "phpunit/phpunit": "^10.2"
+    }
+}
+"#
+        );
+    }
+
+    fn settings_override(php_version: Option<Version>) -> Override {
+        Override {
+            php_version,
+            ..Override::default()
+        }
+    }
+
+    #[test]
+    fn highest_php_version() {
+        let overrides = [
+            settings_override(Some(Version::V7_4)),
+            settings_override(None),
+        ];
+
+        assert_eq!(php_version(Version::V8_1, &overrides), Version::V8_1);
+
+        let overrides = [
+            settings_override(Some(Version::V7_4)),
+            settings_override(Some(Version::V8_2)),
+        ];
+
+        assert_eq!(php_version(Version::V8_1, &overrides), Version::V8_2);
+        assert_eq!(php_version(Version::V8_0, []), Version::V8_0);
+    }
+
+    #[test]
+    fn write_php_requirement() {
+        let dir = std::env::temp_dir().join(format!("java-to-php-composer-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("composer.json");
+
+        fs::write(
+            &path,
+            r#"{"name": "acme/shop", "require": {"php": ">=7.4", "brick/math": "^0.10"}}"#,
+        )
+        .unwrap();
+
+        let overrides = [settings_override(Some(Version::V7_4))];
+        let requirements = [Requirement::from(&LIBRARIES[0])];
+        let autoload = autoload([Some("App\\Models")]);
+
+        write(
+            &path,
+            autoload,
+            &requirements,
+            php_version(Version::V8_1, &overrides),
+        )
+        .unwrap();
+
+        let composer: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(composer["name"], "acme/shop");
+        assert_eq!(composer["require"]["php"], ">=8.1");
+        assert_eq!(composer["require"]["brick/math"], "^0.10");
+        assert_eq!(composer["autoload"]["psr-4"]["App\\"], "src/App/");
+    }
+}
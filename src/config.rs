@@ -0,0 +1,260 @@
+use crate::{backend::BackendKind, overload::Strategy, php::Version, toml};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use ignore::overrides::OverrideBuilder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Name of the configuration file, looked up from the source upwards.
+pub const FILE_NAME: &str = ".java-to-php.toml";
+
+/// Options given by name, as on the command line.
+mod value_enum {
+    use clap::ValueEnum;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: ValueEnum, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value.as_ref().and_then(T::to_possible_value) {
+            Some(value) => serializer.serialize_str(value.get_name()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T: ValueEnum, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error> {
+        let Some(name) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+
+        T::from_str(&name, false).map(Some).map_err(|_| {
+            let names = T::value_variants()
+                .iter()
+                .filter_map(T::to_possible_value)
+                .map(|value| format!("`{}`", value.get_name()))
+                .collect::<Vec<_>>();
+
+            D::Error::custom(format!(
+                "unknown value `{}`, expected one of {}",
+                name,
+                names.join(", ")
+            ))
+        })
+    }
+}
+
+/// Settings for the files matching some globs, relative to the source.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Override {
+    pub paths: Vec<String>,
+    #[serde(with = "value_enum")]
+    pub php_version: Option<Version>,
+    pub strict_types: Option<bool>,
+    pub max_repairs: Option<usize>,
+    pub post_process: Option<Vec<String>>,
+}
+
+impl Override {
+    /// Matches the paths it applies to, relative to the source.
+    pub fn matcher(&self, source: &Path) -> Result<ignore::overrides::Override> {
+        let mut builder = OverrideBuilder::new(source);
+
+        for glob in &self.paths {
+            builder.add(glob)?;
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+/// Contents of the configuration file, which the options given on the
+/// command line take precedence over.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    #[serde(with = "value_enum")]
+    pub backend: Option<BackendKind>,
+    pub base_url: Option<String>,
+    pub model: Option<String>,
+    pub context_window: Option<usize>,
+    pub max_continuations: Option<usize>,
+    pub max_retries: Option<u32>,
+    pub timeout: Option<u64>,
+    pub jobs: Option<usize>,
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u32>,
+    pub cache_dir: Option<PathBuf>,
+    pub pricing: Option<PathBuf>,
+    pub strict_types: Option<bool>,
+    pub keep_broken: Option<bool>,
+    pub max_repairs: Option<usize>,
+    pub psr4: Option<bool>,
+    #[serde(with = "value_enum")]
+    pub overloads: Option<Strategy>,
+    #[serde(with = "value_enum")]
+    pub php_version: Option<Version>,
+    pub glossary: Option<PathBuf>,
+    /// Globs of the Java files to convert, relative to the source
+    pub include: Vec<String>,
+    /// Globs of the files to leave out, relative to the source
+    pub exclude: Vec<String>,
+    pub hidden: Option<bool>,
+    pub no_ignore_vcs: Option<bool>,
+    pub follow_links: Option<bool>,
+    /// Commands run on each converted file, given as `{file}` or appended
+    pub post_process: Vec<String>,
+    /// Namespaces by package prefix
+    pub namespace_map: BTreeMap<String, String>,
+    /// Composer packages by Maven or Gradle group or artifact
+    pub dependency_map: BTreeMap<String, String>,
+    /// Prompt templates by directory, relative to the source
+    pub templates: BTreeMap<PathBuf, PathBuf>,
+    pub overrides: Vec<Override>,
+}
+
+impl Config {
+    /// Finds the configuration file of a source file or directory, in it or
+    /// the closest of its parents.
+    pub fn discover(source: &Path) -> Option<PathBuf> {
+        let source = fs::canonicalize(source).ok()?;
+
+        source
+            .ancestors()
+            .map(|dir| dir.join(FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Loads a configuration file, with the paths it contains relative to it.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).wrap_err_with(|| eyre!("{}", path.display()))?;
+
+        let mut config = toml::parse(&text)
+            .map_err(|e| eyre!(e))
+            .and_then(|table| Ok(serde_json::from_value::<Self>(Value::Object(table))?))
+            .wrap_err_with(|| eyre!("{}", path.display()))?;
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+
+        for path in [
+            &mut config.cache_dir,
+            &mut config.pricing,
+            &mut config.glossary,
+        ]
+        .into_iter()
+        .flatten()
+        .chain(config.templates.values_mut())
+        {
+            *path = dir.join(&path);
+        }
+
+        Ok(config)
+    }
+
+    /// Commands run on the converted files, including those of the overrides.
+    pub fn hooks(&self) -> Vec<String> {
+        let mut hooks = self.post_process.clone();
+
+        for command in self
+            .overrides
+            .iter()
+            .flat_map(|settings_override| settings_override.post_process.iter().flatten())
+        {
+            if !hooks.contains(command) {
+                hooks.push(command.clone());
+            }
+        }
+
+        hooks
+    }
+
+    /// Leaves out the commands run on the converted files.
+    pub fn remove_hooks(&mut self) {
+        self.post_process.clear();
+
+        for settings_override in &mut self.overrides {
+            settings_override.post_process = None;
+        }
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        match serde_json::to_value(self)? {
+            Value::Object(table) => Ok(toml::to_string(&table)),
+            _ => Err(eyre!("Configuration is not a table")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load() {
+        let dir = std::env::temp_dir().join(format!("java-to-php-config-{}", std::process::id()));
+        let source = dir.join("src").join("main");
+        fs::create_dir_all(&source).unwrap();
+        let path = dir.join(FILE_NAME);
+
+        fs::write(
+            &path,
+            "backend = \"chat\"\nglossary = \"glossary.txt\"\npost-process = [\"php -l\"]\n\n\
+            [templates]\nlegacy = \"prompts/legacy.txt\"\n\n[[overrides]]\npaths = [\"legacy/**\"]\n\
+            php-version = \"7.4\"\npost-process = [\"php -l\", \"php-cs-fixer fix\"]\n",
+        )
+        .unwrap();
+
+        let discovered = Config::discover(&source);
+        let canonical = fs::canonicalize(&path).ok();
+        let mut config = Config::load(&path).unwrap();
+
+        fs::write(&path, "backend = \"claude\"\n").unwrap();
+        let unknown_value = Config::load(&path).unwrap_err();
+        fs::write(&path, "model = \"gpt-4\"\nmodels = []\n").unwrap();
+        let unknown_key = Config::load(&path).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(discovered, canonical);
+        assert_eq!(config.backend, Some(BackendKind::Chat));
+        assert_eq!(config.glossary, Some(dir.join("glossary.txt")));
+        assert_eq!(
+            config.templates[Path::new("legacy")],
+            dir.join("prompts/legacy.txt")
+        );
+        assert_eq!(config.overrides[0].php_version, Some(Version::V7_4));
+        assert_eq!(config.hooks(), ["php -l", "php-cs-fixer fix"]);
+
+        config.remove_hooks();
+        assert!(config.hooks().is_empty());
+
+        assert!(format!("{:#}", unknown_value).contains(
+            "unknown value `claude`, expected one of `completions`, `chat`, `compatible`"
+        ));
+        assert!(format!("{:#}", unknown_key).contains("unknown field `models`"));
+    }
+
+    #[test]
+    fn override_paths() {
+        let settings_override = Override {
+            paths: vec!["legacy/**".to_owned(), "*.gen.java".to_owned()],
+            ..Override::default()
+        };
+
+        let matcher = settings_override.matcher(Path::new("src")).unwrap();
+        let matches = |path| matcher.matched(Path::new(path), false).is_whitelist();
+
+        assert!(matches("legacy/a/A.java"));
+        assert!(matches("core/A.gen.java"));
+        assert!(!matches("core/A.java"));
+    }
+}
@@ -0,0 +1,577 @@
+use crate::{
+    backend::{Backend, Prompt, Usage},
+    cache::Cache,
+    chunk, config, java,
+    overload::{self, Rename, Strategy},
+    php::{self, SyntaxError, Version},
+    postprocess, prompt, split,
+    symbols::Index,
+    template::Templates,
+    tokens,
+    walk::SourceFile,
+};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex},
+};
+
+/// Tokens a conversion is expected to take.
+pub struct Estimate {
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    /// Whether the file can be converted in one go rather than in chunks
+    pub fits: bool,
+}
+
+pub struct Conversion {
+    pub usage: Usage,
+    /// Explanations the model gave besides the code
+    pub notes: Vec<String>,
+    /// Files the output was split into, one per type, if it declares several
+    pub split: Vec<PathBuf>,
+    /// Calls that may be to overloaded methods under their original name
+    pub warnings: Vec<String>,
+}
+
+/// Settings that can differ between files.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub strict_types: bool,
+    /// Maximum number of requests to fix syntax errors
+    pub max_repairs: usize,
+    /// PHP version the output must run on
+    pub php_version: Version,
+    /// Commands run on the written files
+    pub post_process: Vec<String>,
+}
+
+pub struct Converter {
+    pub backend: Arc<dyn Backend>,
+    pub context_size: usize,
+    pub max_continuations: usize,
+    pub cache: Option<Cache>,
+    /// Write invalid output aside instead of over the previous output
+    pub keep_broken: bool,
+    pub overloads: Strategy,
+    pub settings: Settings,
+    /// Settings of the files matching some globs, the last match winning
+    pub overrides: Vec<(ignore::overrides::Override, config::Override)>,
+    pub index: Index,
+    pub templates: Templates,
+    /// Terms of the project and how to translate them
+    pub glossary: Option<String>,
+    /// PHP signatures of the files converted so far, by relative path
+    pub signatures: Mutex<BTreeMap<PathBuf, String>>,
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+fn report(errors: &[SyntaxError]) -> String {
+    errors
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Runs commands on a written file, given as `{file}` or else appended.
+fn post_process(commands: &[String], path: &Path) -> Result<()> {
+    let quoted = format!("'{}'", path.display().to_string().replace('\'', r"'\''"));
+
+    for command in commands {
+        let command = match command.contains("{file}") {
+            true => command.replace("{file}", &quoted),
+            false => format!("{} {}", command, quoted),
+        };
+
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .output()
+            .wrap_err_with(|| eyre!("{}", command))?;
+
+        if !output.status.success() {
+            return Err(eyre!(
+                "`{}` failed with {}\n{}",
+                command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Indents the methods of a class body unless the model already did.
+fn indent(methods: &str) -> String {
+    if methods.starts_with([' ', '\t']) {
+        return methods.to_owned();
+    }
+
+    methods
+        .lines()
+        .map(|line| match line.is_empty() {
+            true => String::new(),
+            false => format!("    {}", line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Converter {
+    fn tokens(&self, text: &str) -> usize {
+        tokens::count(self.backend.model(), text)
+    }
+
+    /// Expected size of the PHP equivalent of some Java code.
+    fn expected_tokens(&self, java: &str) -> usize {
+        // PHP tends to take a quarter more tokens than the equivalent Java
+        self.tokens(java) * 5 / 4
+    }
+
+    /// Whether a prompt leaves enough room for an answer of about `expected` tokens.
+    fn fits(&self, prompt: &Prompt, expected: usize) -> bool {
+        tokens::count_prompt(self.backend.model(), prompt) + expected <= self.context_size
+    }
+
+    /// Requests completions until the model stops on its own, returning `None`
+    /// if the answer is still truncated after the allowed continuations.
+    async fn try_complete(&self, prompt: &Prompt, usage: &mut Usage) -> Result<Option<String>> {
+        let mut completion = self.backend.complete(prompt).await?;
+        *usage += completion.usage;
+        let mut text = std::mem::take(&mut completion.text);
+
+        for _ in 0..self.max_continuations {
+            if !completion.is_truncated() {
+                break;
+            }
+
+            completion = self.backend.complete(&prompt.continuation(&text)).await?;
+            *usage += completion.usage;
+            text.push_str(&completion.text);
+        }
+
+        Ok((!completion.is_truncated()).then_some(text))
+    }
+
+    async fn complete(&self, prompt: &Prompt, usage: &mut Usage) -> Result<String> {
+        self.try_complete(prompt, usage).await?.ok_or_else(|| {
+            eyre!(
+                "Output still truncated after {} continuations",
+                self.max_continuations
+            )
+        })
+    }
+
+    pub async fn convert(&self, file: &SourceFile) -> Result<Conversion> {
+        let content = fs::read_to_string(&file.path)?;
+        let (declared, called) = self.renames(file, &content);
+        let settings = self.settings(file);
+        let context = self.context(file, &content);
+        let namespace = context.namespace;
+        let mut usage = Usage::default();
+        let mut notes = Vec::new();
+        let prompt = prompt::conversion(&content, &context);
+        let key = self.cache_key(&prompt)?;
+
+        // Responses are cached as received so that their notes are kept
+        let cached = match &self.cache {
+            Some(cache) => cache.get(&key)?,
+            None => None,
+        };
+
+        let mut fresh = cached.is_none();
+
+        let mut response = match cached {
+            Some(cached) => cached,
+            None => {
+                let complete = match self.fits(&prompt, self.expected_tokens(&content)) {
+                    true => self.try_complete(&prompt, &mut usage).await?,
+                    false => None,
+                };
+
+                match complete {
+                    Some(text) => text,
+                    None => {
+                        self.convert_chunked(&content, &context, &mut usage, &mut notes)
+                            .await?
+                    }
+                }
+            }
+        };
+
+        let mut repairs = 0;
+
+        let (extracted, code, errors) = loop {
+            let extracted = postprocess::extract(&response);
+            let code = postprocess::normalize(&extracted.code, settings.strict_types, namespace);
+            let mut errors = php::validate(&code, settings.php_version);
+
+            if errors.is_empty() {
+                errors = overload::check(
+                    &code,
+                    self.overloads,
+                    &declared,
+                    &called,
+                    self.index.methods(&content),
+                );
+            }
+
+            if errors.is_empty() || repairs == settings.max_repairs {
+                break (extracted, code, errors);
+            }
+
+            let prompt = prompt::repair(&content, &code, &report(&errors), &context);
+
+            // Files converted in chunks are usually too large to repair in one go
+            if !self.fits(&prompt, self.tokens(&code)) {
+                break (extracted, code, errors);
+            }
+
+            response = self.complete(&prompt, &mut usage).await?;
+            fresh = true;
+            repairs += 1;
+        };
+
+        notes.extend(extracted.notes);
+        let destination_file_path = file.new_path.as_path();
+
+        if errors.is_empty() {
+            let methods = self.index.methods(&content);
+            let warnings = overload::unchecked(&code, self.overloads, &declared, &called, methods)
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+
+            if let (Some(cache), true) = (&self.cache, fresh) {
+                cache.put(&key, &response)?;
+            }
+
+            let package = java::package(&content).ok().flatten();
+            let neighbours = self.index.package_types(package.as_deref());
+
+            let Some(files) = split::split(&code, &content, destination_file_path, &neighbours)
+            else {
+                fs::write(destination_file_path, &code)?;
+                self.record(file, &code);
+                post_process(&settings.post_process, destination_file_path)?;
+
+                return Ok(Conversion {
+                    usage,
+                    notes,
+                    split: Vec::new(),
+                    warnings,
+                });
+            };
+
+            for (path, code) in &files {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+
+                fs::write(path, code)?;
+            }
+
+            let (split, codes): (Vec<_>, Vec<_>) = files.into_iter().unzip();
+            self.record(file, &codes.join("\n"));
+
+            for path in &split {
+                post_process(&settings.post_process, path)?;
+            }
+
+            return Ok(Conversion {
+                usage,
+                notes,
+                split,
+                warnings,
+            });
+        }
+
+        if self.keep_broken {
+            let broken = with_suffix(destination_file_path, ".broken");
+            fs::write(&broken, code)?;
+
+            let report = errors
+                .iter()
+                .map(|e| format!("{}:{}\n", broken.display(), e))
+                .collect::<String>();
+
+            fs::write(with_suffix(destination_file_path, ".errors"), report)?;
+        } else {
+            fs::write(destination_file_path, code)?;
+        }
+
+        Err(match repairs {
+            0 => eyre!("Invalid PHP\n{}", report(&errors)),
+            _ => eyre!("Invalid PHP after {} repairs\n{}", repairs, report(&errors)),
+        })
+    }
+
+    /// The settings of a file, with those of the overrides matching it.
+    fn settings(&self, file: &SourceFile) -> Settings {
+        let mut settings = self.settings.clone();
+
+        for (matcher, settings_override) in &self.overrides {
+            if !matcher.matched(&file.relative_path, false).is_whitelist() {
+                continue;
+            }
+
+            let config::Override {
+                paths: _,
+                php_version,
+                strict_types,
+                max_repairs,
+                post_process,
+            } = settings_override.clone();
+
+            settings.php_version = php_version.unwrap_or(settings.php_version);
+            settings.strict_types = strict_types.unwrap_or(settings.strict_types);
+            settings.max_repairs = max_repairs.unwrap_or(settings.max_repairs);
+            settings.post_process = post_process.unwrap_or(settings.post_process);
+        }
+
+        settings
+    }
+
+    /// Everything a file is converted with besides its source, with as many
+    /// signatures of related types as fit in a quarter of the context window,
+    /// in PHP for those already converted.
+    fn context<'a>(&'a self, file: &'a SourceFile, content: &str) -> prompt::Context<'a> {
+        let signatures = self.signatures.lock().unwrap();
+        let mut budget = self.context_size / 4;
+        let mut related = Vec::new();
+        let mut converted = Vec::new();
+
+        for (path, summary) in self.index.related(content) {
+            let php = signatures.get(path);
+
+            // Files declaring several of the types
+            if php.is_some_and(|php| converted.contains(php)) {
+                continue;
+            }
+
+            let tokens = self.tokens(php.map_or(summary, String::as_str));
+
+            if tokens > budget {
+                continue;
+            }
+
+            budget -= tokens;
+
+            match php {
+                Some(php) => converted.push(php.clone()),
+                None => related.push(summary),
+            }
+        }
+
+        let (declared, called) = self.renames(file, content);
+
+        prompt::Context {
+            path: &file.relative_path,
+            package: java::package(content).ok().flatten(),
+            namespace: file.namespace.as_deref(),
+            related,
+            converted,
+            renames: declared.into_iter().chain(called).collect(),
+            overloads: self.overloads,
+            php_version: self.settings(file).php_version,
+            template: self.templates.get(&file.relative_path),
+            glossary: self.glossary.as_deref(),
+        }
+    }
+
+    /// PHP names of the overloads a file declares, and of those it may call
+    /// under another name.
+    fn renames<'a>(
+        &'a self,
+        file: &SourceFile,
+        content: &str,
+    ) -> (Vec<&'a Rename>, Vec<&'a Rename>) {
+        let declared = self
+            .index
+            .renames(&file.relative_path)
+            .iter()
+            .collect::<Vec<_>>();
+
+        let mut called = Vec::new();
+
+        // Dispatched methods are called by their original name
+        if self.overloads == Strategy::Suffix {
+            let related = self.index.related(content);
+            let files = related.iter().map(|(path, _)| *path);
+
+            for rename in files
+                .chain([file.relative_path.as_path()])
+                .flat_map(|path| self.index.callable(path))
+            {
+                if !declared.contains(&rename) && !called.contains(&rename) {
+                    called.push(rename);
+                }
+            }
+        }
+
+        (declared, called)
+    }
+
+    /// Records the signatures of a converted file for the files referring to it.
+    pub fn record(&self, file: &SourceFile, code: &str) {
+        if let Ok(signatures) = php::signatures(code) {
+            self.signatures
+                .lock()
+                .unwrap()
+                .insert(file.relative_path.clone(), signatures);
+        }
+    }
+
+    /// Estimates the tokens a conversion would take without sending anything.
+    pub fn estimate(&self, file: &SourceFile) -> Result<Estimate> {
+        let content = fs::read_to_string(&file.path)?;
+        let prompt = prompt::conversion(&content, &self.context(file, &content));
+        let expected = self.expected_tokens(&content);
+
+        Ok(Estimate {
+            prompt_tokens: tokens::count_prompt(self.backend.model(), &prompt),
+            completion_tokens: expected,
+            fits: self.fits(&prompt, expected),
+        })
+    }
+
+    /// Everything the output depends on, since the prompts of chunked
+    /// conversions derive from the same source and settings.
+    fn cache_key(&self, prompt: &Prompt) -> Result<String> {
+        let messages = serde_json::to_string(&prompt.chat_messages())?;
+
+        Ok(Cache::key([
+            env!("CARGO_PKG_VERSION"),
+            &format!("{:?}", self.backend.kind()),
+            self.backend.url(),
+            self.backend.model(),
+            &self.context_size.to_string(),
+            &self.max_continuations.to_string(),
+            &messages,
+        ]))
+    }
+
+    async fn convert_chunked(
+        &self,
+        content: &str,
+        context: &prompt::Context<'_>,
+        usage: &mut Usage,
+        notes: &mut Vec<String>,
+    ) -> Result<String> {
+        let file = java::parse(content).wrap_err("File too large and could not be split")?;
+
+        let plan = chunk::plan(content, &file)
+            .ok_or_else(|| eyre!("File too large and has no methods to split off"))?;
+
+        // Reserve the same room for the answer as for the methods to convert
+        let overhead = tokens::count_prompt(
+            self.backend.model(),
+            &prompt::members(&plan.skeleton, &plan.type_name, "", context),
+        );
+
+        let budget = self.context_size.saturating_sub(overhead) * 4 / 9;
+
+        if budget == 0 {
+            return Err(eyre!(
+                "Skeleton of `{}` does not fit in the context window",
+                plan.type_name
+            ));
+        }
+
+        let shell = self
+            .complete(&prompt::shell(&plan.shell, &plan.type_name, context), usage)
+            .await?;
+
+        let shell = postprocess::extract(&shell);
+        notes.extend(shell.notes);
+        let shell = shell.code;
+        let mut methods = Vec::new();
+
+        for chunk in plan.chunks(budget, |text| self.tokens(text)) {
+            let prompt = prompt::members(&plan.skeleton, &plan.type_name, &chunk, context);
+            let converted = postprocess::extract(&self.complete(&prompt, usage).await?);
+            notes.extend(converted.notes);
+            methods.push(indent(converted.code.trim_matches('\n')));
+        }
+
+        let end = php::type_body_end(&shell, &plan.type_name)?
+            .ok_or_else(|| eyre!("`{}` not found in the converted skeleton", plan.type_name))?;
+
+        Ok(format!(
+            "{}\n\n{}\n{}",
+            shell[..end].trim_end(),
+            methods.join("\n\n"),
+            &shell[end..]
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{BackendKind, Message, Role};
+
+    fn converter(kind: BackendKind, base_url: Option<&str>) -> Converter {
+        let backend = kind
+            .build(reqwest::Client::new(), base_url, Some("gpt-4o".to_owned()))
+            .unwrap();
+
+        Converter {
+            backend,
+            context_size: 8192,
+            max_continuations: 2,
+            cache: None,
+            keep_broken: false,
+            overloads: Strategy::Suffix,
+            settings: Settings {
+                strict_types: false,
+                max_repairs: 1,
+                php_version: Version::V8_1,
+                post_process: Vec::new(),
+            },
+            overrides: Vec::new(),
+            index: Index::default(),
+            templates: Templates::load(&[]).unwrap(),
+            glossary: None,
+            signatures: Mutex::default(),
+        }
+    }
+
+    #[test]
+    fn cache_key_depends_on_backend() {
+        let prompt = Prompt {
+            messages: vec![Message::new(Role::User, "class A {}")],
+            partial: None,
+        };
+
+        let key = |kind, base_url| converter(kind, base_url).cache_key(&prompt).unwrap();
+        let chat = key(BackendKind::Chat, None);
+
+        assert_eq!(
+            chat,
+            key(BackendKind::Chat, Some("https://api.openai.com/v1/"))
+        );
+        assert_ne!(chat, key(BackendKind::Completions, None));
+        assert_ne!(
+            chat,
+            key(BackendKind::Compatible, Some("https://api.openai.com/v1"))
+        );
+        assert_ne!(
+            chat,
+            key(BackendKind::Chat, Some("http://localhost:8080/v1"))
+        );
+    }
+}
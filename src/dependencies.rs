@@ -0,0 +1,514 @@
+use color_eyre::{eyre::Context, Result};
+use std::{fmt, fs, path::Path, str::FromStr};
+
+/// Build files whose dependencies are read.
+pub const BUILD_FILES: &[&str] = &["pom.xml", "build.gradle", "build.gradle.kts"];
+
+/// Gradle configurations declaring dependencies of the code itself.
+const CONFIGURATIONS: &[&str] = &[
+    "api",
+    "compile",
+    "compileOnly",
+    "implementation",
+    "runtimeOnly",
+    "testCompile",
+    "testCompileOnly",
+    "testImplementation",
+    "testRuntimeOnly",
+];
+
+/// Known replacements, in the syntax of `--dependency-map`.
+const DEFAULT_MAPPINGS: &[&str] = &[
+    "junit=phpunit/phpunit:^10.2",
+    "org.junit=phpunit/phpunit:^10.2",
+    "org.testng=phpunit/phpunit:^10.2",
+    "org.assertj=phpunit/phpunit:^10.2",
+    "org.mockito=mockery/mockery:^1.6",
+    "org.hamcrest=hamcrest/hamcrest-php:^2.0",
+    "org.slf4j=psr/log:^3.0",
+    "ch.qos.logback=monolog/monolog:^3.4",
+    "org.apache.logging.log4j=monolog/monolog:^3.4",
+    "com.fasterxml.jackson=symfony/serializer:^6.3",
+    "com.google.code.gson=symfony/serializer:^6.3",
+    "org.yaml:snakeyaml=symfony/yaml:^6.3",
+    "joda-time=nesbot/carbon:^2.67",
+    "org.apache.httpcomponents=guzzlehttp/guzzle:^7.7",
+    "com.squareup.okhttp3=guzzlehttp/guzzle:^7.7",
+    "org.apache.commons:commons-csv=league/csv:^9.10",
+    "com.google.inject=php-di/php-di:^7.0",
+    "org.projectlombok=",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// The accessor of references to a version catalog, e.g. `libs.guava`
+    pub group: String,
+    /// Empty for references to a version catalog
+    pub artifact: String,
+    pub version: Option<String>,
+    /// Only needed by tests
+    pub test: bool,
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.artifact.is_empty() {
+            return write!(f, "{} (version catalog)", self.group);
+        }
+
+        write!(f, "{}:{}", self.group, self.artifact)?;
+
+        if let Some(version) = &self.version {
+            write!(f, ":{}", version)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Replaces the Java artifacts of a group, or a single one, with a PHP
+/// package, e.g. `org.slf4j=psr/log:^3.0`, or with nothing for `org.projectlombok=`.
+#[derive(Debug, Clone)]
+pub struct Mapping {
+    pub group: String,
+    pub artifact: Option<String>,
+    /// Package name and version constraint
+    pub package: Option<(String, String)>,
+}
+
+impl fmt::Display for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.group)?;
+
+        if let Some(artifact) = &self.artifact {
+            write!(f, ":{}", artifact)?;
+        }
+
+        write!(f, "=")?;
+
+        if let Some((package, version)) = &self.package {
+            write!(f, "{}:{}", package, version)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Mapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (artifact, package) = s.split_once('=').ok_or_else(|| {
+            format!(
+                "Expected GROUP[:ARTIFACT]=[PACKAGE[:VERSION]], found `{}`",
+                s
+            )
+        })?;
+
+        let (group, artifact) = match artifact.trim().split_once(':') {
+            Some((group, artifact)) => (group, Some(artifact.to_owned())),
+            None => (artifact.trim(), None),
+        };
+
+        let package = match package.trim() {
+            "" => None,
+            package => {
+                let (name, version) = package.split_once(':').unwrap_or((package, "*"));
+                Some((name.to_owned(), version.to_owned()))
+            }
+        };
+
+        Ok(Self {
+            group: group.to_owned(),
+            artifact,
+            package,
+        })
+    }
+}
+
+impl Mapping {
+    fn matches(&self, dependency: &Dependency) -> bool {
+        let group = match dependency.group.strip_prefix(self.group.as_str()) {
+            Some(rest) => rest.is_empty() || rest.starts_with('.'),
+            None => false,
+        };
+
+        group
+            && self
+                .artifact
+                .as_ref()
+                .is_none_or(|artifact| *artifact == dependency.artifact)
+    }
+}
+
+fn most_specific<'a>(mappings: &'a [Mapping], dependency: &Dependency) -> Option<&'a Mapping> {
+    mappings
+        .iter()
+        .filter(|mapping| mapping.matches(dependency))
+        .max_by_key(|mapping| (mapping.artifact.is_some(), mapping.group.len()))
+}
+
+/// The most specific of the given mappings for a dependency, or else of the
+/// default ones.
+pub fn find(mappings: &[Mapping], dependency: &Dependency) -> Option<Mapping> {
+    most_specific(mappings, dependency).cloned().or_else(|| {
+        let defaults = DEFAULT_MAPPINGS
+            .iter()
+            .map(|mapping| mapping.parse().unwrap())
+            .collect::<Vec<_>>();
+
+        most_specific(&defaults, dependency).cloned()
+    })
+}
+
+/// Text of the first element named `name` within `xml`.
+fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", name))? + name.len() + 2;
+    let end = xml[start..].find(&format!("</{}>", name))? + start;
+    Some(xml[start..end].trim())
+}
+
+/// Removes the elements named `name`, along with their contents.
+fn remove_elements(xml: &str, name: &str) -> String {
+    let (open, close) = (format!("<{}>", name), format!("</{}>", name));
+    let mut xml = xml.to_owned();
+
+    while let Some(start) = xml.find(&open) {
+        let end = xml[start..]
+            .find(&close)
+            .map_or(xml.len(), |n| start + n + close.len());
+
+        xml.replace_range(start..end, "");
+    }
+
+    xml
+}
+
+/// Dependencies of a Maven POM, leaving out managed versions and plugins.
+pub fn parse_pom(pom: &str) -> Vec<Dependency> {
+    let mut xml = pom.to_owned();
+
+    while let Some(start) = xml.find("<!--") {
+        let end = xml[start..]
+            .find("-->")
+            .map_or(xml.len(), |n| start + n + 3);
+        xml.replace_range(start..end, "");
+    }
+
+    for name in ["dependencyManagement", "plugins", "pluginManagement"] {
+        xml = remove_elements(&xml, name);
+    }
+
+    xml.split("<dependency>")
+        .skip(1)
+        .filter_map(|dependency| {
+            let dependency = &dependency[..dependency.find("</dependency>")?];
+
+            Some(Dependency {
+                group: element(dependency, "groupId")?.to_owned(),
+                artifact: element(dependency, "artifactId")?.to_owned(),
+                version: element(dependency, "version").map(str::to_owned),
+                test: element(dependency, "scope") == Some("test"),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    /// Names, with their dots, e.g. `libs.guava`
+    Word(&'a str),
+    /// Contents of a quoted string
+    String(&'a str),
+    Punct(char),
+}
+
+/// Tokens of a line of a build script, up to a comment.
+fn tokens(line: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = line;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if rest.starts_with("//") {
+            break;
+        } else if c == '"' || c == '\'' {
+            let Some(end) = rest[1..].find(c) else {
+                break;
+            };
+
+            tokens.push(Token::String(&rest[1..end + 1]));
+            rest = &rest[end + 2..];
+        } else if c.is_alphanumeric() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+
+            tokens.push(Token::Word(&rest[..end]));
+            rest = &rest[end..];
+        } else {
+            tokens.push(Token::Punct(c));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    tokens
+}
+
+/// Dependencies declared in Gradle build scripts, in Groovy or Kotlin, as
+/// `group:artifact:version` strings, with named group, name and version, or
+/// as references to a version catalog. Project and platform dependencies are
+/// left out.
+pub fn parse_gradle(script: &str) -> Vec<Dependency> {
+    let mut dependencies = Vec::new();
+
+    for line in script.lines() {
+        let tokens = tokens(line);
+
+        let (configuration, mut rest) = match tokens.split_first() {
+            Some((Token::Word(configuration), rest)) if CONFIGURATIONS.contains(configuration) => {
+                (*configuration, rest)
+            }
+            _ => continue,
+        };
+
+        if rest.first() == Some(&Token::Punct('(')) {
+            rest = &rest[1..];
+        }
+
+        let test = configuration.starts_with("test");
+
+        let dependency = match rest {
+            [Token::String(notation), ..] => {
+                let mut parts = notation.split(':');
+
+                match (parts.next(), parts.next()) {
+                    (Some(group), Some(artifact)) => Some(Dependency {
+                        group: group.to_owned(),
+                        artifact: artifact.to_owned(),
+                        version: parts.next().map(str::to_owned),
+                        test,
+                    }),
+                    _ => None,
+                }
+            }
+            [Token::Word(reference), ..] if reference.starts_with("libs.") => Some(Dependency {
+                group: reference.to_string(),
+                artifact: String::new(),
+                version: None,
+                test,
+            }),
+            // `group: 'g', name: 'a', version: 'v'` or `group = "g", name = "a", version = "v"`
+            [Token::Word(_), Token::Punct(':' | '='), ..] => {
+                let named = |key: &str| {
+                    rest.windows(3).find_map(|window| match window {
+                        [Token::Word(word), Token::Punct(':' | '='), Token::String(value)]
+                            if *word == key =>
+                        {
+                            Some(*value)
+                        }
+                        _ => None,
+                    })
+                };
+
+                named("group")
+                    .zip(named("name"))
+                    .map(|(group, artifact)| Dependency {
+                        group: group.to_owned(),
+                        artifact: artifact.to_owned(),
+                        version: named("version").map(str::to_owned),
+                        test,
+                    })
+            }
+            // e.g. `project(":core")`, `platform("g:bom:v")` or `fileTree("libs")`
+            _ => None,
+        };
+
+        dependencies.extend(dependency);
+    }
+
+    dependencies
+}
+
+/// Dependencies of a build file, by its name.
+pub fn parse(path: impl AsRef<Path>) -> Result<Vec<Dependency>> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path).wrap_err_with(|| format!("{}", path.display()))?;
+
+    Ok(match path.extension().is_some_and(|ext| ext == "xml") {
+        true => parse_pom(&content),
+        false => parse_gradle(&content),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependency(notation: &str, test: bool) -> Dependency {
+        let mut parts = notation.split(':');
+
+        Dependency {
+            group: parts.next().unwrap().to_owned(),
+            artifact: parts.next().unwrap_or_default().to_owned(),
+            version: parts.next().map(str::to_owned),
+            test,
+        }
+    }
+
+    #[test]
+    fn groovy() {
+        let script = r#"
+plugins {
+    id 'java-library'
+}
+
+dependencies {
+    implementation 'com.google.guava:guava:32.1.2-jre'
+    api project(':core')
+    implementation platform('org.springframework.boot:spring-boot-dependencies:3.1.2')
+    implementation group: 'org.slf4j', name: 'slf4j-api', version: '2.0.7'
+    runtimeOnly group: 'org.example', classifier: 'name:', name: 'native'
+    compileOnly "org.projectlombok:lombok" // name: 'commented'
+    implementation libs.jackson.databind
+    testImplementation 'org.junit.jupiter:junit-jupiter:5.10.0'
+    implementation fileTree(dir: 'libs', include: ['*.jar'])
+}
+"#;
+
+        assert_eq!(
+            parse_gradle(script),
+            [
+                dependency("com.google.guava:guava:32.1.2-jre", false),
+                dependency("org.slf4j:slf4j-api:2.0.7", false),
+                dependency("org.example:native", false),
+                dependency("org.projectlombok:lombok", false),
+                dependency("libs.jackson.databind", false),
+                dependency("org.junit.jupiter:junit-jupiter:5.10.0", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn kotlin() {
+        let script = r#"
+dependencies {
+    api(project(":core"))
+    implementation(platform("org.springframework.boot:spring-boot-dependencies:3.1.2"))
+    implementation(enforcedPlatform("com.example:bom:1.0"))
+    implementation("com.squareup.okhttp3:okhttp:4.11.0") {
+        exclude(group = "org.jetbrains.kotlin")
+    }
+    implementation(group = "org.yaml", name = "snakeyaml", version = "2.0")
+    implementation(libs.guava)
+    testImplementation(libs.junit.jupiter)
+    testRuntimeOnly("org.junit.platform:junit-platform-launcher")
+}
+"#;
+
+        assert_eq!(
+            parse_gradle(script),
+            [
+                dependency("com.squareup.okhttp3:okhttp:4.11.0", false),
+                dependency("org.yaml:snakeyaml:2.0", false),
+                dependency("libs.guava", false),
+                dependency("libs.junit.jupiter", true),
+                dependency("org.junit.platform:junit-platform-launcher", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn catalog_references_are_unmapped() {
+        let dependency = dependency("libs.guava", false);
+
+        assert_eq!(dependency.to_string(), "libs.guava (version catalog)");
+        assert!(find(&[], &dependency).is_none());
+
+        let mappings = ["libs.guava=php/guava".parse::<Mapping>().unwrap()];
+        assert!(find(&mappings, &dependency).is_some());
+    }
+
+    #[test]
+    fn pom() {
+        let pom = r#"<project>
+    <dependencyManagement>
+        <dependencies>
+            <dependency>
+                <groupId>org.springframework.boot</groupId>
+                <artifactId>spring-boot-dependencies</artifactId>
+                <version>3.1.2</version>
+            </dependency>
+        </dependencies>
+    </dependencyManagement>
+    <dependencies>
+        <!--
+        <dependency>
+            <groupId>commented</groupId>
+            <artifactId>out</artifactId>
+        </dependency>
+        -->
+        <dependency>
+            <groupId>org.slf4j</groupId>
+            <artifactId>slf4j-api</artifactId>
+            <version>2.0.7</version>
+        </dependency>
+        <dependency>
+            <groupId>com.fasterxml.jackson.core</groupId>
+            <artifactId>jackson-databind</artifactId>
+        </dependency>
+        <dependency>
+            <groupId>junit</groupId>
+            <artifactId>junit</artifactId>
+            <version>4.13.2</version>
+            <scope>test</scope>
+        </dependency>
+    </dependencies>
+    <build>
+        <plugins>
+            <plugin>
+                <dependencies>
+                    <dependency>
+                        <groupId>org.ow2.asm</groupId>
+                        <artifactId>asm</artifactId>
+                    </dependency>
+                </dependencies>
+            </plugin>
+        </plugins>
+    </build>
+</project>
+"#;
+
+        assert_eq!(
+            parse_pom(pom),
+            [
+                dependency("org.slf4j:slf4j-api:2.0.7", false),
+                dependency("com.fasterxml.jackson.core:jackson-databind", false),
+                dependency("junit:junit:4.13.2", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn mappings() {
+        let slf4j = dependency("org.slf4j:slf4j-api:2.0.7", false);
+        let found = find(&[], &slf4j).unwrap();
+        assert_eq!(found.to_string(), "org.slf4j=psr/log:^3.0");
+
+        let mappings = [
+            "org=acme/org".parse::<Mapping>().unwrap(),
+            "org.slf4j:slf4j-api=acme/log:^1.0".parse().unwrap(),
+        ];
+        assert_eq!(
+            find(&mappings, &slf4j).unwrap().to_string(),
+            "org.slf4j:slf4j-api=acme/log:^1.0"
+        );
+
+        let lombok = dependency("org.projectlombok:lombok", false);
+        assert_eq!(find(&[], &lombok).unwrap().package, None);
+
+        // Groups match by segment
+        assert!(find(&[], &dependency("org.slf4jx:other", false)).is_none());
+    }
+}
@@ -0,0 +1,172 @@
+/// Groups the nodes of a dependency graph, given by the dependencies of each
+/// node, into waves whose nodes only depend on those of earlier waves or on
+/// each other when part of the same cycle.
+pub fn waves(dependencies: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let components = components(dependencies);
+    let mut component_of = vec![0; dependencies.len()];
+
+    for (i, component) in components.iter().enumerate() {
+        for &node in component {
+            component_of[node] = i;
+        }
+    }
+
+    // Components come after the ones they depend on
+    let mut wave_of = vec![0; components.len()];
+
+    for (i, component) in components.iter().enumerate() {
+        wave_of[i] = component
+            .iter()
+            .flat_map(|&node| &dependencies[node])
+            .map(|&dependency| component_of[dependency])
+            .filter(|&dependency| dependency != i)
+            .map(|dependency| wave_of[dependency] + 1)
+            .max()
+            .unwrap_or(0);
+    }
+
+    let mut waves = vec![Vec::new(); wave_of.iter().max().map_or(0, |&max| max + 1)];
+
+    for (node, &component) in component_of.iter().enumerate() {
+        waves[wave_of[component]].push(node);
+    }
+
+    waves
+}
+
+/// Strongly connected components, each after the ones it depends on.
+fn components(dependencies: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct Tarjan<'a> {
+        dependencies: &'a [Vec<usize>],
+        index: Vec<Option<usize>>,
+        low_link: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        next_index: usize,
+        components: Vec<Vec<usize>>,
+    }
+
+    impl Tarjan<'_> {
+        fn enter(&mut self, node: usize) {
+            self.index[node] = Some(self.next_index);
+            self.low_link[node] = self.next_index;
+            self.next_index += 1;
+            self.stack.push(node);
+            self.on_stack[node] = true;
+        }
+
+        /// Visits the nodes reachable from one with a stack of the nodes being
+        /// visited and of their next dependency, as long chains of
+        /// dependencies would overflow the call stack.
+        fn visit(&mut self, root: usize) {
+            self.enter(root);
+            let mut visiting = vec![(root, 0)];
+
+            while let Some(&(node, next)) = visiting.last() {
+                if let Some(&dependency) = self.dependencies[node].get(next) {
+                    let last = visiting.len() - 1;
+                    visiting[last].1 += 1;
+
+                    match self.index[dependency] {
+                        None => {
+                            self.enter(dependency);
+                            visiting.push((dependency, 0));
+                        }
+                        Some(index) if self.on_stack[dependency] => {
+                            self.low_link[node] = self.low_link[node].min(index);
+                        }
+                        Some(_) => {}
+                    }
+
+                    continue;
+                }
+
+                visiting.pop();
+
+                if let Some(&(caller, _)) = visiting.last() {
+                    self.low_link[caller] = self.low_link[caller].min(self.low_link[node]);
+                }
+
+                if Some(self.low_link[node]) == self.index[node] {
+                    let mut component = Vec::new();
+
+                    while let Some(member) = self.stack.pop() {
+                        self.on_stack[member] = false;
+                        component.push(member);
+
+                        if member == node {
+                            break;
+                        }
+                    }
+
+                    self.components.push(component);
+                }
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        dependencies,
+        index: vec![None; dependencies.len()],
+        low_link: vec![0; dependencies.len()],
+        stack: Vec::new(),
+        on_stack: vec![false; dependencies.len()],
+        next_index: 0,
+        components: Vec::new(),
+    };
+
+    for node in 0..dependencies.len() {
+        if tarjan.index[node].is_none() {
+            tarjan.visit(node);
+        }
+    }
+
+    tarjan.components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chain() {
+        assert_eq!(waves(&[vec![1], vec![2], vec![]]), [[2], [1], [0]]);
+        assert_eq!(waves(&[]), Vec::<Vec<usize>>::new());
+    }
+
+    #[test]
+    fn cycles() {
+        assert_eq!(waves(&[vec![1], vec![0], vec![0]]), [vec![0, 1], vec![2]]);
+        assert_eq!(
+            waves(&[vec![1], vec![2], vec![0, 3], vec![], vec![2]]),
+            [vec![3], vec![0, 1, 2], vec![4]]
+        );
+    }
+
+    #[test]
+    fn self_imports() {
+        assert_eq!(waves(&[vec![0], vec![0, 1]]), [[0], [1]]);
+    }
+
+    #[test]
+    fn disconnected() {
+        assert_eq!(
+            waves(&[vec![], vec![3], vec![0], vec![]]),
+            [vec![0, 3], vec![1, 2]]
+        );
+    }
+
+    #[test]
+    fn long_chains() {
+        let n = 200_000;
+        let chain = (0..n)
+            .map(|i| (i + 1..n).take(1).collect())
+            .collect::<Vec<_>>();
+        let waves_of_chain = waves(&chain);
+        assert_eq!(waves_of_chain.len(), n);
+        assert_eq!(waves_of_chain[0], [n - 1]);
+
+        let cycle = (0..n).map(|i| vec![(i + 1) % n]).collect::<Vec<_>>();
+        assert_eq!(waves(&cycle), [(0..n).collect::<Vec<_>>()]);
+    }
+}
@@ -0,0 +1,1045 @@
+use color_eyre::{eyre::eyre, Result};
+use std::{collections::BTreeSet, ops::Range};
+
+const MODIFIERS: &[&str] = &[
+    "public",
+    "protected",
+    "private",
+    "static",
+    "abstract",
+    "final",
+    "strictfp",
+    "sealed",
+    "default",
+    "synchronized",
+    "native",
+    "transient",
+    "volatile",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Ident,
+    Literal,
+    Punct(char),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
+
+fn lex(source: &str) -> Result<Vec<Token>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    let unterminated =
+        |what: &str, start: usize| eyre!("{}: Unterminated {}", line_of(source, start), what);
+
+    while i < bytes.len() {
+        let start = i;
+        let c = source[i..].chars().next().unwrap_or_default();
+
+        if c.is_whitespace() {
+            i += c.len_utf8();
+        } else if source[i..].starts_with("//") {
+            i = source[i..].find('\n').map_or(bytes.len(), |n| i + n);
+        } else if source[i..].starts_with("/*") {
+            i = source[i + 2..]
+                .find("*/")
+                .map(|n| i + 2 + n + 2)
+                .ok_or_else(|| unterminated("comment", start))?;
+        } else if source[i..].starts_with("\"\"\"") {
+            i += 3;
+
+            loop {
+                match bytes.get(i) {
+                    None => return Err(unterminated("text block", start)),
+                    Some(b'\\') => i += 2,
+                    Some(b'"') if source[i..].starts_with("\"\"\"") => break i += 3,
+                    Some(_) => i += 1,
+                }
+            }
+
+            tokens.push(Token {
+                kind: TokenKind::Literal,
+                start,
+                end: i,
+            });
+        } else if c == '"' || c == '\'' {
+            i += 1;
+
+            loop {
+                match bytes.get(i) {
+                    None | Some(b'\n') => return Err(unterminated("literal", start)),
+                    Some(b'\\') => i += 2,
+                    Some(&b) if b == c as u8 => break i += 1,
+                    Some(_) => i += 1,
+                }
+            }
+
+            tokens.push(Token {
+                kind: TokenKind::Literal,
+                start,
+                end: i,
+            });
+        } else if c.is_ascii_digit() {
+            while let Some(&b) = bytes.get(i) {
+                let exponent_sign = (b == b'+' || b == b'-')
+                    && matches!(bytes[i - 1], b'e' | b'E' | b'p' | b'P')
+                    && !source[start..].starts_with("0x");
+
+                if b.is_ascii_alphanumeric() || b == b'_' || b == b'.' || exponent_sign {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+
+            tokens.push(Token {
+                kind: TokenKind::Literal,
+                start,
+                end: i,
+            });
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            i = source[i..]
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                .map_or(bytes.len(), |n| i + n);
+
+            tokens.push(Token {
+                kind: TokenKind::Ident,
+                start,
+                end: i,
+            });
+        } else {
+            i += c.len_utf8();
+
+            tokens.push(Token {
+                kind: TokenKind::Punct(c),
+                start,
+                end: i,
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeKind {
+    Class,
+    Interface,
+    Enum,
+    Record,
+    Annotation,
+}
+
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub path: String,
+    pub wildcard: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Method {
+    pub name: String,
+    pub modifiers: Vec<String>,
+    /// `None` for constructors
+    pub return_type: Option<String>,
+    /// Types of the parameters, with `...` for varargs
+    pub params: Vec<String>,
+    /// Declaration without leading comments and body, whitespace collapsed
+    pub signature: String,
+    /// Braces included, `None` for abstract and interface methods
+    pub body: Option<Range<usize>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub modifiers: Vec<String>,
+    /// Declaration with whitespace collapsed
+    pub declaration: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum MemberKind {
+    Field(Field),
+    Method(Method),
+    Constructor(Method),
+    Initializer,
+    EnumConstants(Vec<String>),
+    Type(TypeDecl),
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub kind: MemberKind,
+    /// Includes leading comments and annotations
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeDecl {
+    pub kind: TypeKind,
+    pub name: String,
+    pub modifiers: Vec<String>,
+    pub extends: Vec<String>,
+    pub implements: Vec<String>,
+    /// Includes leading comments and annotations
+    pub span: Range<usize>,
+    pub members: Vec<Member>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JavaFile {
+    pub package: Option<String>,
+    pub imports: Vec<Import>,
+    pub types: Vec<TypeDecl>,
+}
+
+pub fn parse(source: &str) -> Result<JavaFile> {
+    let tokens = lex(source)?;
+
+    Parser {
+        source,
+        tokens,
+        pos: 0,
+    }
+    .file()
+}
+
+/// Identifiers used in a file, comments and literals excluded.
+pub fn identifiers(source: &str) -> Result<BTreeSet<&str>> {
+    Ok(lex(source)?
+        .into_iter()
+        .filter(|token| token.kind == TokenKind::Ident)
+        .map(|token| &source[token.start..token.end])
+        .collect())
+}
+
+/// Collapses runs of whitespace to a single space.
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Reads the package declaration only, so that it is known even if the rest
+/// of the file cannot be parsed.
+pub fn package(source: &str) -> Result<Option<String>> {
+    let mut parser = Parser {
+        source,
+        tokens: lex(source)?,
+        pos: 0,
+    };
+
+    // Package annotations
+    parser.modifiers()?;
+
+    if !parser.is_ident("package") {
+        return Ok(None);
+    }
+
+    parser.pos += 1;
+    parser.qualified_name().map(Some)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<Token> {
+        self.tokens.get(self.pos + offset).copied()
+    }
+
+    fn text(&self, token: Token) -> &'a str {
+        &self.source[token.start..token.end]
+    }
+
+    fn is_punct(&self, c: char) -> bool {
+        matches!(self.peek(), Some(Token { kind: TokenKind::Punct(p), .. }) if p == c)
+    }
+
+    fn is_ident(&self, text: &str) -> bool {
+        matches!(self.peek(), Some(token) if token.kind == TokenKind::Ident && self.text(token) == text)
+    }
+
+    fn error(&self, message: &str) -> color_eyre::Report {
+        match self.peek() {
+            Some(token) => eyre!(
+                "{}: {} near `{}`",
+                line_of(self.source, token.start),
+                message,
+                self.text(token)
+            ),
+            None => eyre!("{} at end of file", message),
+        }
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self.peek().ok_or_else(|| self.error("Unexpected end"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<Token> {
+        if !self.is_punct(c) {
+            return Err(self.error(&format!("Expected `{}`", c)));
+        }
+
+        self.next()
+    }
+
+    fn ident(&mut self) -> Result<&'a str> {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::Ident => {
+                self.pos += 1;
+                Ok(self.text(token))
+            }
+            _ => Err(self.error("Expected identifier")),
+        }
+    }
+
+    /// Skips from an opening delimiter to just past its matching closing one,
+    /// returning the closing token.
+    fn skip_balanced(&mut self, open: char, close: char) -> Result<Token> {
+        self.expect_punct(open)?;
+        let mut depth = 1;
+
+        loop {
+            let token = self
+                .next()
+                .map_err(|_| self.error(&format!("Unbalanced `{}`", open)))?;
+
+            match token.kind {
+                TokenKind::Punct(c) if c == open => depth += 1,
+                TokenKind::Punct(c) if c == close => {
+                    depth -= 1;
+
+                    if depth == 0 {
+                        return Ok(token);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn qualified_name(&mut self) -> Result<String> {
+        let mut name = self.ident()?.to_owned();
+
+        while self.is_punct('.') && matches!(self.peek_at(1), Some(t) if t.kind == TokenKind::Ident)
+        {
+            self.pos += 1;
+            name.push('.');
+            name.push_str(self.ident()?);
+        }
+
+        Ok(name)
+    }
+
+    fn annotation(&mut self) -> Result<()> {
+        self.expect_punct('@')?;
+        self.qualified_name()?;
+
+        if self.is_punct('(') {
+            self.skip_balanced('(', ')')?;
+        }
+
+        Ok(())
+    }
+
+    fn is_annotation_type(&self) -> bool {
+        self.is_punct('@')
+            && matches!(self.peek_at(1), Some(t) if t.kind == TokenKind::Ident && self.text(t) == "interface")
+    }
+
+    fn modifiers(&mut self) -> Result<Vec<String>> {
+        let mut modifiers = Vec::new();
+
+        loop {
+            if self.is_punct('@') && !self.is_annotation_type() {
+                self.annotation()?;
+            } else if self.is_ident("non")
+                && matches!(self.peek_at(1), Some(t) if t.kind == TokenKind::Punct('-'))
+            {
+                self.pos += 3;
+                modifiers.push("non-sealed".to_owned());
+            } else if matches!(self.peek(), Some(t) if t.kind == TokenKind::Ident && MODIFIERS.contains(&self.text(t)))
+            {
+                let token = self.next()?;
+                modifiers.push(self.text(token).to_owned());
+            } else {
+                return Ok(modifiers);
+            }
+        }
+    }
+
+    fn type_kind(&self) -> Option<TypeKind> {
+        if self.is_annotation_type() {
+            return Some(TypeKind::Annotation);
+        }
+
+        let token = self.peek().filter(|t| t.kind == TokenKind::Ident)?;
+
+        match self.text(token) {
+            "class" => Some(TypeKind::Class),
+            "interface" => Some(TypeKind::Interface),
+            "enum" => Some(TypeKind::Enum),
+            // `record` is a contextual keyword, only a declaration when a name follows
+            "record" if matches!(self.peek_at(1), Some(t) if t.kind == TokenKind::Ident) => {
+                Some(TypeKind::Record)
+            }
+            _ => None,
+        }
+    }
+
+    fn file(mut self) -> Result<JavaFile> {
+        let mut file = JavaFile {
+            package: None,
+            imports: Vec::new(),
+            types: Vec::new(),
+        };
+
+        let mut leading = 0;
+
+        while self.peek().is_some() {
+            if self.is_punct(';') {
+                leading = self.next()?.end;
+                continue;
+            }
+
+            // Package annotations are rare enough to be skipped together with the package
+            let start = self.pos;
+            self.modifiers()?;
+
+            if self.is_ident("package") {
+                self.pos += 1;
+                file.package = Some(self.qualified_name()?);
+                leading = self.expect_punct(';')?.end;
+            } else if self.is_ident("import") {
+                self.pos += 1;
+                if self.is_ident("static") {
+                    self.pos += 1;
+                }
+
+                let mut path = self.qualified_name()?;
+                let wildcard = self.is_punct('.');
+
+                if wildcard {
+                    self.pos += 1;
+                    self.expect_punct('*')?;
+                    path.push_str(".*");
+                }
+
+                file.imports.push(Import { path, wildcard });
+
+                leading = self.expect_punct(';')?.end;
+            } else {
+                self.pos = start;
+                let decl = self.type_decl(leading)?;
+                leading = decl.span.end;
+                file.types.push(decl);
+            }
+        }
+
+        Ok(file)
+    }
+
+    /// Parses a comma separated list of type names, dropping type arguments.
+    fn type_list(&mut self) -> Result<Vec<String>> {
+        let mut types = vec![self.qualified_name()?];
+
+        loop {
+            if self.is_punct('<') {
+                self.skip_balanced('<', '>')?;
+            }
+
+            if !self.is_punct(',') {
+                return Ok(types);
+            }
+
+            self.pos += 1;
+            types.push(self.qualified_name()?);
+        }
+    }
+
+    fn type_decl(&mut self, leading: usize) -> Result<TypeDecl> {
+        let modifiers = self.modifiers()?;
+        let kind = self
+            .type_kind()
+            .ok_or_else(|| self.error("Expected type declaration"))?;
+
+        if kind == TypeKind::Annotation {
+            self.pos += 1;
+        }
+
+        self.pos += 1;
+        let name = self.ident()?.to_owned();
+        let mut extends = Vec::new();
+        let mut implements = Vec::new();
+
+        while !self.is_punct('{') {
+            if self.is_punct('<') {
+                self.skip_balanced('<', '>')?;
+            } else if self.is_punct('(') {
+                self.skip_balanced('(', ')')?;
+            } else if self.is_ident("extends") {
+                self.pos += 1;
+                extends = self.type_list()?;
+            } else if self.is_ident("implements") {
+                self.pos += 1;
+                implements = self.type_list()?;
+            } else if self.is_ident("permits") {
+                self.pos += 1;
+                self.type_list()?;
+            } else {
+                return Err(self.error("Unexpected token in type declaration"));
+            }
+        }
+
+        let open = self.next()?;
+        let mut members = Vec::new();
+        let mut leading_member = open.end;
+
+        if kind == TypeKind::Enum {
+            let member = self.enum_constants(leading_member)?;
+            leading_member = member.span.end;
+            members.push(member);
+        }
+
+        while !self.is_punct('}') {
+            if self.peek().is_none() {
+                return Err(self.error(&format!("Unclosed body of `{}`", name)));
+            }
+
+            if self.is_punct(';') {
+                leading_member = self.next()?.end;
+                continue;
+            }
+
+            let member = self.member(leading_member, &name)?;
+            leading_member = member.span.end;
+            members.push(member);
+        }
+
+        let close = self.next()?;
+
+        Ok(TypeDecl {
+            kind,
+            name,
+            modifiers,
+            extends,
+            implements,
+            span: leading..close.end,
+            members,
+        })
+    }
+
+    fn enum_constants(&mut self, leading: usize) -> Result<Member> {
+        let mut constants = Vec::new();
+        let mut end = leading;
+
+        loop {
+            if self.is_punct('}') {
+                break;
+            }
+
+            if self.is_punct(';') {
+                end = self.next()?.end;
+                break;
+            }
+
+            if self.is_punct(',') {
+                end = self.next()?.end;
+                continue;
+            }
+
+            self.modifiers()?;
+            let token = self.peek();
+            constants.push(self.ident()?.to_owned());
+            end = token.map_or(end, |t| t.end);
+
+            if self.is_punct('(') {
+                end = self.skip_balanced('(', ')')?.end;
+            }
+
+            if self.is_punct('{') {
+                end = self.skip_balanced('{', '}')?.end;
+            }
+        }
+
+        Ok(Member {
+            kind: MemberKind::EnumConstants(constants),
+            span: leading..end,
+        })
+    }
+
+    fn member(&mut self, leading: usize, type_name: &str) -> Result<Member> {
+        let start = self.pos;
+        let modifiers = self.modifiers()?;
+
+        if self.type_kind().is_some() {
+            self.pos = start;
+            let decl = self.type_decl(leading)?;
+
+            return Ok(Member {
+                span: decl.span.clone(),
+                kind: MemberKind::Type(decl),
+            });
+        }
+
+        if self.is_punct('{') {
+            let close = self.skip_balanced('{', '}')?;
+
+            return Ok(Member {
+                kind: MemberKind::Initializer,
+                span: leading..close.end,
+            });
+        }
+
+        let decl_start = self.tokens[start].start;
+
+        if self.is_punct('<') {
+            self.skip_balanced('<', '>')?;
+        }
+
+        let type_start = self.pos;
+
+        // Look ahead for what decides the kind of member
+        let mut angle_depth = 0;
+
+        let decider = loop {
+            let token = self.peek().ok_or_else(|| self.error("Unexpected end"))?;
+
+            match token.kind {
+                TokenKind::Punct('<') => angle_depth += 1,
+                TokenKind::Punct('>') => angle_depth -= 1,
+                TokenKind::Punct(c @ ('(' | '=' | ';' | '{')) if angle_depth == 0 => break c,
+                TokenKind::Punct(',') if angle_depth == 0 => break ',',
+                _ => {}
+            }
+
+            self.pos += 1;
+        };
+
+        match decider {
+            '(' | '{' => {
+                let name_token = self.tokens[self.pos - 1];
+                let name = self.text(name_token).to_owned();
+
+                let return_type = (self.pos - 1 > type_start).then(|| {
+                    normalize(
+                        &self.source[self.tokens[type_start].start..self.tokens[self.pos - 2].end],
+                    )
+                });
+
+                // Compact record constructors have no parameter list
+                let params = match decider {
+                    '(' => self.params()?,
+                    _ => Vec::new(),
+                };
+
+                while !self.is_punct('{') && !self.is_punct(';') {
+                    self.next()
+                        .map_err(|_| self.error(&format!("Unterminated method `{}`", name)))?;
+                }
+
+                let signature_end = self.tokens[self.pos - 1].end;
+
+                let (body, end) = if self.is_punct('{') {
+                    let open = self.peek().map_or(0, |t| t.start);
+                    let close = self.skip_balanced('{', '}')?;
+                    (Some(open..close.end), close.end)
+                } else {
+                    (None, self.next()?.end)
+                };
+
+                let method = Method {
+                    signature: normalize(&self.source[decl_start..signature_end]),
+                    name,
+                    modifiers,
+                    return_type,
+                    params,
+                    body,
+                };
+
+                let kind = if method.return_type.is_none() && method.name == type_name {
+                    MemberKind::Constructor(method)
+                } else {
+                    MemberKind::Method(method)
+                };
+
+                Ok(Member {
+                    kind,
+                    span: leading..end,
+                })
+            }
+            _ => {
+                self.pos = type_start;
+                self.field(leading, decl_start, modifiers)
+            }
+        }
+    }
+
+    fn params(&mut self) -> Result<Vec<String>> {
+        let open = self.pos;
+        self.skip_balanced('(', ')')?;
+        let close = self.pos - 1;
+        let mut params = Vec::new();
+        let mut param_start = open + 1;
+        let mut depth = 0;
+
+        for i in open + 1..close {
+            match self.tokens[i].kind {
+                TokenKind::Punct('<' | '(') => depth += 1,
+                TokenKind::Punct('>' | ')') => depth -= 1,
+                TokenKind::Punct(',') if depth == 0 => {
+                    params.extend(self.param(param_start..i));
+                    param_start = i + 1;
+                }
+                _ => {}
+            }
+        }
+
+        if param_start < close {
+            params.extend(self.param(param_start..close));
+        }
+
+        Ok(params)
+    }
+
+    /// Type of a parameter.
+    fn param(&self, tokens: Range<usize>) -> Option<String> {
+        let mut tokens = &self.tokens[tokens];
+
+        // Annotations and `final`
+        loop {
+            match tokens {
+                [first, ..] if first.kind == TokenKind::Ident && self.text(*first) == "final" => {
+                    tokens = &tokens[1..];
+                }
+                [at, _, rest @ ..] if at.kind == TokenKind::Punct('@') => {
+                    tokens = rest;
+
+                    while let [dot, _, rest @ ..] = tokens {
+                        if dot.kind != TokenKind::Punct('.') {
+                            break;
+                        }
+
+                        tokens = rest;
+                    }
+
+                    if let [open, ..] = tokens {
+                        if open.kind == TokenKind::Punct('(') {
+                            let close = tokens
+                                .iter()
+                                .position(|t| t.kind == TokenKind::Punct(')'))?;
+                            tokens = &tokens[close + 1..];
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        // C-style array declarators: `int values[]`
+        let mut dims = 0;
+
+        while let [rest @ .., open, close] = tokens {
+            if open.kind != TokenKind::Punct('[') || close.kind != TokenKind::Punct(']') {
+                break;
+            }
+
+            dims += 1;
+            tokens = rest;
+        }
+
+        let (name, ty) = tokens.split_last()?;
+
+        if name.kind != TokenKind::Ident || self.text(*name) == "this" || ty.is_empty() {
+            return None;
+        }
+
+        let ty = normalize(&self.source[ty[0].start..ty[ty.len() - 1].end]);
+        let (ty, varargs) = match ty.strip_suffix("...") {
+            Some(ty) => (ty.trim_end(), "..."),
+            None => (ty.as_str(), ""),
+        };
+
+        Some(format!("{}{}{}", ty, "[]".repeat(dims), varargs))
+    }
+
+    fn field(
+        &mut self,
+        leading: usize,
+        decl_start: usize,
+        modifiers: Vec<String>,
+    ) -> Result<Member> {
+        let mut depth = 0;
+
+        let end = loop {
+            let token = self
+                .next()
+                .map_err(|_| self.error("Unterminated field declaration"))?;
+
+            match token.kind {
+                TokenKind::Punct('(' | '{' | '[') => depth += 1,
+                TokenKind::Punct(')' | '}' | ']') => depth -= 1,
+                TokenKind::Punct(';') if depth == 0 => break token.end,
+                _ => {}
+            }
+        };
+
+        Ok(Member {
+            kind: MemberKind::Field(Field {
+                modifiers,
+                declaration: normalize(&self.source[decl_start..end]),
+            }),
+            span: leading..end,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"package com.example.shop;
+
+import java.util.*;
+import static java.util.Map.Entry;
+import com.example.Util;
+
+/** Orders. */
+@Entity
+@Table(name = "orders")
+public final class Order<T extends Comparable<? super T>> extends Base<T> implements Comparable<Order<T>>, java.io.Serializable {
+    private static final Map<String, List<int[]>> CACHE = new HashMap<>();
+    @Deprecated protected int a = 1, b[] = {2};
+
+    static { CACHE.clear(); }
+
+    /** Creates one. */
+    @Inject
+    public Order(@Named("x") final List<? extends T> items, String... names) throws Exception {
+        super(items);
+    }
+
+    public <R> Map<String, R> map(java.util.function.Function<? super T, ? extends R> f, int[][] grid) {
+        return null;
+    }
+
+    @Override
+    public int compareTo(Order<T> other) { return 0; }
+
+    abstract static class Inner {}
+}
+
+record Point(int x, @NonNull List<String> names) implements Shape {
+    Point {
+        if (x < 0) throw new IllegalArgumentException();
+    }
+
+    static Point origin() { return new Point(0, List.of()); }
+}
+
+enum Planet implements HasMass {
+    MERCURY(3.303e+23) {
+        @Override double mass() { return 1; }
+    },
+    EARTH(5.976e+24);
+
+    private final double mass;
+
+    Planet(double mass) { this.mass = mass; }
+
+    double mass() { return mass; }
+}
+
+interface Shape {
+    double area();
+    default String name() { return "shape"; }
+}
+
+@interface Marker {
+    String value() default "";
+}
+"#;
+
+    fn methods(decl: &TypeDecl) -> Vec<&Method> {
+        decl.members
+            .iter()
+            .filter_map(|member| match &member.kind {
+                MemberKind::Method(method) | MemberKind::Constructor(method) => Some(method),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn file() {
+        let file = parse(SOURCE).unwrap();
+
+        assert_eq!(file.package.as_deref(), Some("com.example.shop"));
+        assert_eq!(
+            file.imports
+                .iter()
+                .map(|import| (import.path.as_str(), import.wildcard))
+                .collect::<Vec<_>>(),
+            [
+                ("java.util.*", true),
+                ("java.util.Map.Entry", false),
+                ("com.example.Util", false)
+            ]
+        );
+
+        assert_eq!(
+            file.types
+                .iter()
+                .map(|decl| (decl.kind, decl.name.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (TypeKind::Class, "Order"),
+                (TypeKind::Record, "Point"),
+                (TypeKind::Enum, "Planet"),
+                (TypeKind::Interface, "Shape"),
+                (TypeKind::Annotation, "Marker"),
+            ]
+        );
+    }
+
+    #[test]
+    fn generics_and_annotations() {
+        let file = parse(SOURCE).unwrap();
+        let order = &file.types[0];
+
+        assert_eq!(order.modifiers, ["public", "final"]);
+        assert_eq!(order.extends, ["Base"]);
+        assert_eq!(order.implements, ["Comparable", "java.io.Serializable"]);
+        assert!(SOURCE[order.span.clone()]
+            .trim_start()
+            .starts_with("/** Orders. */\n@Entity"));
+
+        let fields = order
+            .members
+            .iter()
+            .filter_map(|member| match &member.kind {
+                MemberKind::Field(field) => Some(field.declaration.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            fields,
+            [
+                "private static final Map<String, List<int[]>> CACHE = new HashMap<>();",
+                "@Deprecated protected int a = 1, b[] = {2};",
+            ]
+        );
+
+        let methods = methods(order);
+        let [constructor, map, compare] = methods.as_slice() else {
+            panic!("{:#?}", methods);
+        };
+
+        assert_eq!(constructor.return_type, None);
+        assert_eq!(constructor.params, ["List<? extends T>", "String..."]);
+        assert!(constructor.signature.ends_with("throws Exception"));
+        assert!(constructor.body.is_some());
+
+        assert_eq!(map.return_type.as_deref(), Some("Map<String, R>"));
+        assert_eq!(
+            map.params,
+            [
+                "java.util.function.Function<? super T, ? extends R>",
+                "int[][]"
+            ]
+        );
+
+        assert_eq!(
+            compare.signature,
+            "@Override public int compareTo(Order<T> other)"
+        );
+        assert_eq!(&SOURCE[compare.body.clone().unwrap()], "{ return 0; }");
+
+        assert!(matches!(
+            &order.members.last().unwrap().kind,
+            MemberKind::Type(inner) if inner.name == "Inner" && inner.modifiers == ["abstract", "static"]
+        ));
+    }
+
+    #[test]
+    fn records() {
+        let file = parse(SOURCE).unwrap();
+        let point = &file.types[1];
+
+        assert_eq!(point.implements, ["Shape"]);
+
+        let methods = methods(point);
+        let [constructor, origin] = methods.as_slice() else {
+            panic!("{:#?}", methods);
+        };
+
+        // Compact constructor
+        assert_eq!(constructor.name, "Point");
+        assert!(constructor.params.is_empty());
+        assert_eq!(origin.modifiers, ["static"]);
+        assert_eq!(origin.return_type.as_deref(), Some("Point"));
+    }
+
+    #[test]
+    fn enums_with_bodies() {
+        let file = parse(SOURCE).unwrap();
+        let planet = &file.types[2];
+
+        assert!(matches!(
+            &planet.members[0].kind,
+            MemberKind::EnumConstants(constants) if constants == &["MERCURY", "EARTH"]
+        ));
+
+        let signatures = methods(planet)
+            .iter()
+            .map(|method| method.signature.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(signatures, ["Planet(double mass)", "double mass()"]);
+    }
+
+    #[test]
+    fn interfaces() {
+        let file = parse(SOURCE).unwrap();
+        let shape = methods(&file.types[3]);
+
+        assert_eq!(shape[0].body, None);
+        assert_eq!(shape[1].modifiers, ["default"]);
+
+        let marker = methods(&file.types[4]);
+        assert_eq!(marker[0].signature, "String value() default \"\"");
+        assert_eq!(marker[0].body, None);
+    }
+
+    #[test]
+    fn package_only() {
+        assert_eq!(
+            package("@Generated\npackage a.b;\nclass {")
+                .unwrap()
+                .as_deref(),
+            Some("a.b")
+        );
+        assert_eq!(package("class A {}").unwrap(), None);
+        assert!(parse("class A { void f( }").is_err());
+    }
+}
@@ -1,173 +1,1110 @@
-use clap::Parser;
+mod backend;
+mod budget;
+mod cache;
+mod chunk;
+mod composer;
+mod config;
+mod convert;
+mod dependencies;
+mod graph;
+mod java;
+mod manifest;
+mod namespace;
+mod overload;
+mod php;
+mod postprocess;
+mod pricing;
+mod prompt;
+mod ratelimit;
+mod retry;
+mod sha256;
+mod split;
+mod symbols;
+mod template;
+mod tokens;
+mod toml;
+mod walk;
+
+use backend::{is_local, Backend, BackendKind, Usage};
+use budget::{Budget, Metered};
+use cache::Cache;
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
 use color_eyre::{
     eyre::{eyre, Context, ContextCompat},
     Result,
 };
-use ignore::WalkBuilder;
+use composer::Requirement;
+use config::Config;
+use convert::{Conversion, Converter, Settings};
+use dependencies::Mapping;
 use indicatif::ProgressBar;
+use manifest::{Entry, Manifest, Status};
+use namespace::Rule;
+use overload::Strategy;
+use php::Version;
+use pricing::Pricing;
+use ratelimit::{RateLimiter, Throttled};
 use reqwest::{
     header::{HeaderMap, AUTHORIZATION},
     Client,
 };
-use serde::{Deserialize, Serialize};
+use retry::Retrying;
 use std::{
+    collections::BTreeMap,
     fs,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
 };
-use tiktoken_rs::get_completion_max_tokens;
+use symbols::Index;
+use template::Templates;
 use tokio::task::JoinSet;
+use walk::{build_files, walk, Filter, Layout, SourceFile};
 
-#[derive(Debug, Serialize)]
-struct Request {
-    model: &'static str,
-    prompt: String,
-    max_tokens: usize,
-    temperature: f32,
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    subcommand_negates_reqs = true,
+    override_usage = "java-to-php [OPTIONS] <SOURCE> <DESTINATION>\n       java-to-php [OPTIONS] config show [SOURCE]"
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(
+        long,
+        value_name("FILE"),
+        help("Configuration file [default: .java-to-php.toml in the source directory or the closest of its parents]")
+    )]
+    config: Option<PathBuf>,
+    #[arg(
+        long,
+        help("Run the post-process commands of a configuration file found next to the source, not only of one given with --config")
+    )]
+    allow_hooks: bool,
+    #[arg(short('k'), long, env("OPENAI_API_KEY"), help("OpenAI API key"))]
+    api_key: Option<String>,
+    #[arg(short, long, value_enum, default_value_t = BackendKind::Chat, help("LLM backend"))]
+    backend: BackendKind,
+    #[arg(
+        long,
+        env("OPENAI_BASE_URL"),
+        help("Base URL of an OpenAI-compatible API")
+    )]
+    base_url: Option<String>,
+    #[arg(short, long, env("OPENAI_MODEL"), help("Model name"))]
+    model: Option<String>,
+    #[arg(
+        long,
+        help("Context window of the model in tokens [default: known size or 4096]")
+    )]
+    context_window: Option<usize>,
+    #[arg(
+        long,
+        default_value_t = 3,
+        help("Maximum number of requests to continue a truncated output")
+    )]
+    max_continuations: usize,
+    #[arg(
+        long,
+        default_value_t = 5,
+        help("Maximum number of retries of a request failing with a transient error")
+    )]
+    max_retries: u32,
+    #[arg(long, default_value_t = 600, help("Timeout of a request in seconds"))]
+    timeout: u64,
+    #[arg(
+        short,
+        long,
+        default_value_t = 8,
+        help("Number of files converted concurrently")
+    )]
+    jobs: usize,
+    #[arg(long, help("Maximum number of requests per minute"))]
+    requests_per_minute: Option<u32>,
+    #[arg(long, help("Maximum number of tokens per minute"))]
+    tokens_per_minute: Option<u32>,
+    #[arg(
+        long,
+        help("Cache directory [default: <DESTINATION>/.java-to-php/cache]")
+    )]
+    cache_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        conflicts_with("cache_dir"),
+        help("Always convert, ignoring the cache")
+    )]
+    no_cache: bool,
+    #[arg(
+        long,
+        help("Only convert the files left pending or failed by the previous run")
+    )]
+    resume: bool,
+    #[arg(
+        long,
+        help("Print the tokens and cost a conversion would take without converting")
+    )]
+    dry_run: bool,
+    #[arg(
+        long,
+        help("JSON file of prices per 1K tokens by model, e.g. {\"gpt-4\": {\"prompt\": 0.03, \"completion\": 0.06}}")
+    )]
+    pricing: Option<PathBuf>,
+    #[arg(
+        long,
+        help("Stop converting files once the run has cost this many USD")
+    )]
+    max_cost: Option<f64>,
+    #[arg(
+        long,
+        help("Stop converting files once the run has used this many tokens")
+    )]
+    max_tokens_total: Option<usize>,
+    #[arg(
+        long,
+        overrides_with("no_strict_types"),
+        help("Declare strict types in the converted files")
+    )]
+    strict_types: bool,
+    #[arg(
+        long,
+        overrides_with("strict_types"),
+        help("Do not declare strict types, whatever the configuration file says")
+    )]
+    no_strict_types: bool,
+    #[arg(
+        long,
+        overrides_with("no_keep_broken"),
+        help("Write output with syntax errors to <FILE>.php.broken along with <FILE>.php.errors instead of overwriting <FILE>.php")
+    )]
+    keep_broken: bool,
+    #[arg(
+        long,
+        overrides_with("keep_broken"),
+        help("Overwrite <FILE>.php with output with syntax errors, whatever the configuration file says")
+    )]
+    no_keep_broken: bool,
+    #[arg(
+        long,
+        default_value_t = 2,
+        help("Maximum number of requests to fix the syntax errors of a converted file")
+    )]
+    max_repairs: usize,
+    #[arg(
+        long,
+        overrides_with("no_psr4"),
+        help("Place the converted files under <DESTINATION>/src at the path of their namespace and write a composer.json")
+    )]
+    psr4: bool,
+    #[arg(
+        long,
+        overrides_with("psr4"),
+        help("Place the converted files at the same relative path as the source, whatever the configuration file says")
+    )]
+    no_psr4: bool,
+    #[arg(
+        long,
+        value_name("PACKAGE=NAMESPACE"),
+        help("Map the packages starting with a prefix to a namespace, e.g. com.acme=Acme")
+    )]
+    namespace_map: Vec<Rule>,
+    #[arg(
+        long,
+        value_name("GROUP[:ARTIFACT]=[PACKAGE[:VERSION]]"),
+        help("Replace Maven or Gradle dependencies with a Composer package, e.g. com.google.guava=, in addition to the known ones")
+    )]
+    dependency_map: Vec<Mapping>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Strategy::Suffix,
+        help("How to declare overloaded methods, which PHP does not support")
+    )]
+    overloads: Strategy,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Version::default(),
+        help("PHP version the converted code must run on")
+    )]
+    php_version: Version,
+    #[arg(
+        long,
+        value_name("[DIR=]FILE"),
+        help("Build the conversion requests for the files under DIR, relative to the source, or for all files from a template with {{java}}, {{path}}, {{package}}, {{namespace}}, {{php_version}}, {{related}} and {{glossary}} placeholders and {{#if VARIABLE}}...{{else}}...{{/if}} sections")
+    )]
+    template: Vec<template::Rule>,
+    #[arg(
+        long,
+        value_name("FILE"),
+        help("Terms of the project and how to translate them, given along with each file")
+    )]
+    glossary: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name("GLOB"),
+        help(
+            "Only convert the Java files matching a glob relative to the source, e.g. com/acme/**"
+        )
+    )]
+    include: Vec<String>,
+    #[arg(
+        long,
+        value_name("GLOB"),
+        help("Leave out the files matching a glob relative to the source, e.g. **/generated/**, in addition to those listed in .javatophpignore files")
+    )]
+    exclude: Vec<String>,
+    #[arg(
+        long,
+        overrides_with("no_hidden"),
+        help("Convert hidden files and the files of hidden directories")
+    )]
+    hidden: bool,
+    #[arg(
+        long,
+        overrides_with("hidden"),
+        help("Leave out hidden files and directories, whatever the configuration file says")
+    )]
+    no_hidden: bool,
+    #[arg(
+        long,
+        overrides_with("ignore_vcs"),
+        help("Convert the files ignored by .gitignore and other Git excludes")
+    )]
+    no_ignore_vcs: bool,
+    #[arg(
+        long,
+        overrides_with("no_ignore_vcs"),
+        help("Leave out the files ignored by .gitignore and other Git excludes, whatever the configuration file says")
+    )]
+    ignore_vcs: bool,
+    #[arg(long, overrides_with("no_follow_links"), help("Follow symbolic links"))]
+    follow_links: bool,
+    #[arg(
+        long,
+        overrides_with("follow_links"),
+        help("Do not follow symbolic links, whatever the configuration file says")
+    )]
+    no_follow_links: bool,
+    #[arg(required = true, help("Source file or directory"))]
+    source: Option<PathBuf>,
+    #[arg(required = true, help("Destination directory"))]
+    destination: Option<PathBuf>,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
-enum Response {
-    Ok { choices: Vec<Choice> },
-    Err { error: Error },
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "Inspect the configuration")]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
 }
 
-#[derive(Debug, Deserialize)]
-struct Error {
-    message: String,
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    #[command(
+        about = "Print the configuration in effect, from the configuration file and the options before `config`"
+    )]
+    Show {
+        #[arg(help("Source file or directory whose configuration file is used [default: .]"))]
+        source: Option<PathBuf>,
+    },
 }
 
-#[derive(Debug, Deserialize)]
-struct Choice {
-    text: String,
-}
+/// Takes the options that are neither given on the command line nor by an
+/// environment variable from the configuration file, with its mappings and
+/// templates before those given, which take precedence.
+fn configure(args: &mut Args, matches: &ArgMatches, config: &Config) -> Result<()> {
+    let given = |id: &str| {
+        matches
+            .value_source(id)
+            .is_some_and(|source| source != ValueSource::DefaultValue)
+    };
 
-#[derive(Parser, Debug)]
-#[command(version)]
-struct Args {
-    #[arg(short('k'), long, env("OPENAI_API_KEY"), help("OpenAI API key"))]
-    api_key: String,
-    #[arg(help("Source file or directory"))]
-    source: PathBuf,
-    #[arg(help("Destination directory"))]
-    destination: PathBuf,
-}
+    macro_rules! configure {
+        ($($field:ident),*) => {$(
+            if let (Some(value), false) = (&config.$field, given(stringify!($field))) {
+                args.$field = value.clone().into();
+            }
+        )*};
+    }
 
-async fn convert(
-    source_file_path: impl AsRef<Path>,
-    destination_file_path: impl AsRef<Path>,
-    client: &Client,
-) -> Result<()> {
-    let content = fs::read_to_string(source_file_path)?;
-    let model = "text-davinci-003";
-    let prompt = format!("#Java to PHP:\nJava:\n{}\n\nPHP:", content);
-    let max_tokens = get_completion_max_tokens(model, &prompt).map_err(|e| eyre!(e))?;
-
-    let request = Request {
+    configure!(
+        backend,
+        base_url,
         model,
-        prompt,
-        max_tokens,
-        temperature: 0.,
-    };
+        context_window,
+        max_continuations,
+        max_retries,
+        timeout,
+        jobs,
+        requests_per_minute,
+        tokens_per_minute,
+        cache_dir,
+        pricing,
+        max_repairs,
+        overloads,
+        php_version,
+        glossary
+    );
 
-    let response = client
-        .post("https://api.openai.com/v1/completions")
-        .json(&request)
-        .send()
-        .await?
-        .json::<Response>()
-        .await?;
-
-    let choices = match response {
-        Response::Ok { choices } => choices,
-        Response::Err { error } => return Err(eyre!("{}", error.message)),
-    };
+    // Flags given on the command line, either way, win over the file
+    macro_rules! configure_flags {
+        ($(($field:ident, $negation:ident)),*) => {$(
+            if let (Some(value), false, false) = (
+                config.$field,
+                given(stringify!($field)),
+                given(stringify!($negation)),
+            ) {
+                args.$field = value;
+            }
+        )*};
+    }
+
+    configure_flags!(
+        (strict_types, no_strict_types),
+        (keep_broken, no_keep_broken),
+        (psr4, no_psr4),
+        (hidden, no_hidden),
+        (no_ignore_vcs, ignore_vcs),
+        (follow_links, no_follow_links)
+    );
 
-    let new_content = choices
-        .first()
-        .wrap_err("No choice received")?
-        .text
-        .as_str();
+    let namespace_map = config
+        .namespace_map
+        .iter()
+        .map(|(package, namespace)| format!("{}={}", package, namespace).parse())
+        .collect::<Result<Vec<Rule>, _>>()
+        .map_err(|e| eyre!(e))?;
 
-    fs::write(&destination_file_path, new_content)?;
+    let dependency_map = config
+        .dependency_map
+        .iter()
+        .map(|(artifact, package)| format!("{}={}", artifact, package).parse())
+        .collect::<Result<Vec<Mapping>, _>>()
+        .map_err(|e| eyre!(e))?;
+
+    let templates = config.templates.iter().map(|(dir, file)| template::Rule {
+        dir: dir.clone(),
+        file: file.clone(),
+    });
+
+    args.namespace_map.splice(0..0, namespace_map);
+    args.dependency_map.splice(0..0, dependency_map);
+    args.template.splice(0..0, templates);
+    args.include.splice(0..0, config.include.iter().cloned());
+    args.exclude.splice(0..0, config.exclude.iter().cloned());
+
+    // Checked once merged, as the configuration file may set `psr4`
+    if given("namespace_map") && !args.psr4 {
+        return Err(eyre!("--namespace-map requires --psr4"));
+    }
 
     Ok(())
 }
 
+/// The configuration in effect, given the options merged with the
+/// configuration file.
+fn effective(args: &Args, config: Config) -> Config {
+    Config {
+        backend: Some(args.backend),
+        base_url: args.base_url.clone(),
+        model: args.model.clone(),
+        context_window: args.context_window,
+        max_continuations: Some(args.max_continuations),
+        max_retries: Some(args.max_retries),
+        timeout: Some(args.timeout),
+        jobs: Some(args.jobs),
+        requests_per_minute: args.requests_per_minute,
+        tokens_per_minute: args.tokens_per_minute,
+        cache_dir: args.cache_dir.clone(),
+        pricing: args.pricing.clone(),
+        strict_types: Some(args.strict_types),
+        keep_broken: Some(args.keep_broken),
+        max_repairs: Some(args.max_repairs),
+        psr4: Some(args.psr4),
+        overloads: Some(args.overloads),
+        php_version: Some(args.php_version),
+        glossary: args.glossary.clone(),
+        include: args.include.clone(),
+        exclude: args.exclude.clone(),
+        hidden: Some(args.hidden),
+        no_ignore_vcs: Some(args.no_ignore_vcs),
+        follow_links: Some(args.follow_links),
+        namespace_map: args
+            .namespace_map
+            .iter()
+            .map(|rule| (rule.package.clone(), rule.namespace.clone()))
+            .collect(),
+        dependency_map: args
+            .dependency_map
+            .iter()
+            .filter_map(|mapping| {
+                let mapping = mapping.to_string();
+                let (artifact, package) = mapping.split_once('=')?;
+                Some((artifact.to_owned(), package.to_owned()))
+            })
+            .collect(),
+        templates: args
+            .template
+            .iter()
+            .map(|rule| (rule.dir.clone(), rule.file.clone()))
+            .collect(),
+        ..config
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     color_eyre::install()?;
 
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches)?;
+
+    let root = match &args.command {
+        Some(Command::Config {
+            command: ConfigCommand::Show { source },
+        }) => source.clone(),
+        None => args.source.clone(),
+    };
+
+    let config_path = args
+        .config
+        .clone()
+        .or_else(|| Config::discover(root.as_deref().unwrap_or(Path::new("."))));
+
+    let mut config = match &config_path {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+
+    // A checkout could come with a configuration file running anything
+    if let (Some(path), None, false) = (&config_path, &args.config, args.allow_hooks) {
+        if !config.hooks().is_empty() {
+            eprintln!(
+                "Not running the post-process commands of {} without --allow-hooks",
+                path.display()
+            );
+
+            config.remove_hooks();
+        }
+    }
+
+    let hooks = config.hooks();
+
+    configure(&mut args, &matches, &config)?;
+
+    if args.command.is_some() {
+        if let Some(path) = &config_path {
+            println!("# {}\n", path.display());
+        }
+
+        print!("{}", effective(&args, config).to_toml()?);
+
+        return Ok(());
+    }
+
     let Args {
-        source,
-        destination,
+        command: _,
+        config: _,
+        allow_hooks: _,
+        source: Some(source),
+        destination: Some(destination),
         api_key,
-    } = Args::parse();
+        backend,
+        base_url,
+        model,
+        context_window,
+        max_continuations,
+        max_retries,
+        timeout,
+        jobs,
+        requests_per_minute,
+        tokens_per_minute,
+        cache_dir,
+        no_cache,
+        resume,
+        dry_run,
+        pricing,
+        max_cost,
+        max_tokens_total,
+        strict_types,
+        keep_broken,
+        max_repairs,
+        psr4,
+        namespace_map,
+        dependency_map,
+        overloads,
+        php_version,
+        template,
+        glossary,
+        include,
+        exclude,
+        hidden,
+        no_ignore_vcs,
+        follow_links,
+        no_strict_types: _,
+        no_keep_broken: _,
+        no_psr4: _,
+        no_hidden: _,
+        ignore_vcs: _,
+        no_follow_links: _,
+    } = args
+    else {
+        return Err(eyre!("A source and a destination are required"));
+    };
+
+    let filter = Filter {
+        include,
+        exclude,
+        hidden,
+        no_ignore_vcs,
+        follow_links,
+    };
+
+    let overrides = config
+        .overrides
+        .into_iter()
+        .map(|settings_override| Ok((settings_override.matcher(&source)?, settings_override)))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Overrides may target newer versions
+    let highest_php_version = composer::php_version(
+        php_version,
+        overrides
+            .iter()
+            .map(|(_, settings_override)| settings_override),
+    );
 
     let mut headers = HeaderMap::new();
-    headers.insert(AUTHORIZATION, format!("Bearer {}", api_key).parse()?);
 
-    let client = Client::builder().default_headers(headers).build()?;
+    match api_key {
+        Some(api_key) => {
+            headers.insert(AUTHORIZATION, format!("Bearer {}", api_key).parse()?);
+        }
+        None if dry_run || base_url.as_deref().map(is_local).transpose()? == Some(true) => {}
+        None => {
+            return Err(eyre!(
+                "An API key is required unless --base-url is a local server"
+            ))
+        }
+    }
+
+    let client = Client::builder()
+        .default_headers(headers)
+        .timeout(Duration::from_secs(timeout))
+        .build()?;
+
+    let backend = backend.build(client, base_url.as_deref(), model)?;
+    let mut prices = Pricing::default();
+
+    if let Some(pricing) = pricing {
+        prices.extend_from_file(pricing)?;
+    }
+
+    let max_cost = match max_cost {
+        Some(max_cost) => {
+            let name = prices.select(backend.model()).wrap_err_with(|| {
+                eyre!(
+                    "No price known for model {}, see --pricing",
+                    backend.model()
+                )
+            })?;
+
+            Some((max_cost, prices.0[name]))
+        }
+        None => None,
+    };
+
+    let budget = Arc::new(Budget::new(max_tokens_total, max_cost));
+
+    let backend: Arc<dyn Backend> = Arc::new(Metered {
+        inner: Arc::new(Retrying {
+            inner: Arc::new(Throttled {
+                inner: backend,
+                limiter: RateLimiter::new(requests_per_minute, tokens_per_minute),
+            }),
+            max_retries,
+        }),
+        budget: Arc::clone(&budget),
+    });
+
+    let layout = match psr4 {
+        true => Layout::Psr4(namespace_map),
+        false => Layout::Mirror,
+    };
+
+    let files = walk(&source, &destination, &layout, &filter)?;
+
+    let sources = files
+        .iter()
+        .map(|file| {
+            fs::read_to_string(&file.path).wrap_err_with(|| eyre!("{}", file.path.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let index = Index::new(
+        files
+            .iter()
+            .map(|file| file.relative_path.as_path())
+            .zip(sources.iter().map(String::as_str)),
+        overloads,
+    );
+
+    let converter = Arc::new(Converter {
+        context_size: context_window.unwrap_or_else(|| tokens::get_context_size(backend.model())),
+        backend,
+        max_continuations,
+        cache: (!no_cache).then(|| Cache {
+            dir: cache_dir.unwrap_or_else(|| destination.join(".java-to-php").join("cache")),
+        }),
+        keep_broken,
+        overloads,
+        settings: Settings {
+            strict_types,
+            max_repairs,
+            php_version,
+            post_process: config.post_process,
+        },
+        overrides,
+        index,
+        templates: Templates::load(&template)?,
+        glossary: glossary
+            .map(|path| fs::read_to_string(&path).wrap_err_with(|| eyre!("{}", path.display())))
+            .transpose()?,
+        signatures: Default::default(),
+    });
+
+    if dry_run {
+        estimate(&converter, &files, &prices)?;
+        requirements(&source, &dependency_map, &filter)?;
+
+        return Ok(());
+    }
+
+    if !hooks.is_empty() {
+        println!("Post-processing each converted file with:");
+
+        for command in &hooks {
+            println!("  {}", command);
+        }
+
+        println!();
+    }
 
     if !destination.is_dir() {
         return Err(eyre!("{}: Not a directory", destination.display()));
     }
 
     if source.is_file() {
-        let file_name = source.file_name().wrap_err("Invalid file name")?;
-        let mut new_path = destination;
-        new_path.push(file_name);
-        new_path.set_extension("php");
-        return convert(source, new_path, &client).await;
-    }
+        let file = &files[0];
+
+        if let Some(dir) = file.new_path.parent() {
+            fs::create_dir_all(dir).wrap_err_with(|| eyre!("{}", dir.display()))?;
+        }
+
+        let conversion = converter.convert(file).await?;
+
+        for note in conversion.notes {
+            println!("{}\n", note);
+        }
+
+        if !conversion.split.is_empty() {
+            println!("Split into one file per type:");
 
-    if !source.is_dir() {
-        return Err(eyre!("{}: No such file or directory", source.display()));
+            for path in conversion.split {
+                println!("  {}", path.display());
+            }
+        }
+
+        if !conversion.warnings.is_empty() {
+            println!("Calls that may be to overloaded methods:");
+
+            for warning in conversion.warnings {
+                println!("  {}", warning);
+            }
+        }
+
+        println!("{} tokens used", conversion.usage.total_tokens);
+
+        return Ok(());
     }
 
-    let bar = ProgressBar::new(0);
-    let mut tasks = JoinSet::<Result<()>>::new();
+    let manifest_path = destination.join(".java-to-php").join("manifest.json");
 
-    for result in WalkBuilder::new(&source)
-        .filter_entry(|entry| {
-            let path = entry.path();
+    let previous = if resume {
+        Manifest::load(&manifest_path).wrap_err("Nothing to resume")?
+    } else {
+        Manifest::default()
+    };
 
-            path.is_dir()
-                || path.is_file() && path.extension().map(|ext| ext == "java").unwrap_or(false)
+    let autoload = composer::autoload(files.iter().map(|file| file.namespace.as_deref()));
+
+    // Files are converted after those they depend on, so that the PHP
+    // signatures of the latter can be given along with them
+    let positions = files
+        .iter()
+        .enumerate()
+        .map(|(i, file)| (file.relative_path.as_path(), i))
+        .collect::<BTreeMap<_, _>>();
+
+    let dependencies = sources
+        .iter()
+        .map(|source| {
+            converter
+                .index
+                .dependencies(source)
+                .into_iter()
+                .map(|path| positions[path])
+                .collect()
         })
-        .build()
-    {
-        let entry = result?;
-        let path = entry.into_path();
-        let relative_path = path.strip_prefix(&source)?;
-        let mut new_path = destination.clone();
-        new_path.push(relative_path);
-
-        if path.is_file() {
-            new_path.set_extension("php");
-            let client = client.clone();
+        .collect::<Vec<_>>();
+
+    let waves = graph::waves(&dependencies);
+    let mut wave_of = vec![0; files.len()];
+
+    for (wave, nodes) in waves.iter().enumerate() {
+        for &node in nodes {
+            wave_of[node] = wave;
+        }
+    }
+
+    let mut manifest = Manifest::default();
+    let mut pending = vec![Vec::new(); waves.len()];
+
+    for (file, wave) in files.into_iter().zip(wave_of) {
+        let hash = sha256::hex_digest(
+            fs::read(&file.path).wrap_err_with(|| eyre!("{}", file.path.display()))?,
+        );
+
+        let relative_path = &file.relative_path;
+
+        let output = manifest::relative(&file.new_path, &destination);
+
+        if previous.is_done(relative_path, &hash, &output) {
+            let code = previous.files[relative_path]
+                .outputs(&destination)
+                .iter()
+                .map(|path| fs::read_to_string(path).wrap_err_with(|| eyre!("{}", path.display())))
+                .collect::<Result<Vec<_>>>()?;
+
+            converter.record(&file, &code.join("\n"));
+
+            manifest
+                .files
+                .insert(relative_path.clone(), previous.files[relative_path].clone());
+            continue;
+        }
+
+        manifest.files.insert(
+            relative_path.clone(),
+            Entry {
+                status: Status::Pending,
+                output,
+                hash,
+                tokens: 0,
+                error: None,
+                notes: Vec::new(),
+                split: Vec::new(),
+                warnings: Vec::new(),
+            },
+        );
+
+        pending[wave].push(file);
+    }
+
+    manifest.save(&manifest_path)?;
+
+    pending.retain(|wave| !wave.is_empty());
+
+    let bar = ProgressBar::new(pending.iter().map(Vec::len).sum::<usize>() as u64);
+    let mut waves = pending.into_iter();
+    let mut files = Vec::new().into_iter();
+    let mut tasks = JoinSet::<(PathBuf, Result<Conversion>)>::new();
+    let mut total_usage = Usage::default();
+
+    loop {
+        // A wave starts once the files of the previous one are all converted
+        if files.len() == 0 && tasks.is_empty() {
+            match waves.next() {
+                Some(wave) => files = wave.into_iter(),
+                None => break,
+            }
+        }
+
+        // Files already being converted are finished even if they overrun the budget
+        while tasks.len() < jobs.max(1) && !budget.is_exhausted() {
+            let Some(file) = files.next() else {
+                break;
+            };
+
+            if let Some(dir) = file.new_path.parent() {
+                fs::create_dir_all(dir).wrap_err_with(|| eyre!("{}", dir.display()))?;
+            }
+
+            let converter = Arc::clone(&converter);
 
             tasks.spawn(async move {
-                convert(path, &new_path, &client)
+                let result = converter
+                    .convert(&file)
                     .await
-                    .wrap_err_with(|| eyre!("{}", new_path.display()))
+                    .wrap_err_with(|| eyre!("{}", file.new_path.display()));
+
+                (file.relative_path, result)
             });
-        } else if !new_path.exists() {
-            fs::create_dir(&new_path)?;
         }
-    }
 
-    bar.set_length(tasks.len() as u64);
+        let Some((relative_path, result)) = tasks.join_next().await.transpose()? else {
+            break;
+        };
+
+        let entry = manifest
+            .files
+            .get_mut(&relative_path)
+            .wrap_err("File missing from the manifest")?;
 
-    while let Some(result) = tasks.join_next().await.transpose()? {
-        if let Err(e) = result {
-            bar.println(format!("{:#}", e));
+        match result {
+            Ok(Conversion {
+                usage,
+                notes,
+                split,
+                warnings,
+            }) => {
+                entry.status = Status::Done;
+                entry.warnings = warnings;
+                entry.tokens = usage.total_tokens;
+                entry.notes = notes;
+                entry.split = split
+                    .iter()
+                    .map(|path| manifest::relative(path, &destination))
+                    .collect();
+                total_usage += usage;
+            }
+            Err(e) => {
+                entry.status = Status::Failed;
+                entry.error = Some(format!("{:#}", e));
+                bar.println(format!("{:#}", e));
+            }
         }
 
+        // Saved after every file so that an interrupted run can be resumed
+        manifest.save(&manifest_path)?;
         bar.inc(1);
     }
 
     bar.finish();
 
+    let mut requirements = requirements(&source, &dependency_map, &filter)?;
+
+    if psr4 {
+        for entry in manifest.files.values() {
+            if entry.status != Status::Done {
+                continue;
+            }
+
+            for output in entry.outputs(&destination) {
+                let code =
+                    fs::read_to_string(&output).wrap_err_with(|| eyre!("{}", output.display()))?;
+
+                let libraries = composer::libraries(&code).unwrap_or_default();
+                requirements.extend(libraries.into_iter().map(Requirement::from));
+            }
+        }
+
+        requirements.sort_by(|a, b| a.package.cmp(&b.package));
+        requirements.dedup_by(|a, b| a.package == b.package);
+        composer::write(
+            destination.join("composer.json"),
+            autoload,
+            &requirements,
+            highest_php_version,
+        )?;
+    }
+
+    let split = manifest
+        .files
+        .iter()
+        .filter(|(_, entry)| !entry.split.is_empty())
+        .collect::<Vec<_>>();
+
+    if !split.is_empty() {
+        println!("{} files split into one file per type:", split.len());
+
+        for (path, entry) in split {
+            println!("  {}", path.display());
+
+            for output in &entry.split {
+                println!("    {}", destination.join(output).display());
+            }
+        }
+    }
+
+    let warned = manifest
+        .files
+        .iter()
+        .filter(|(_, entry)| !entry.warnings.is_empty())
+        .collect::<Vec<_>>();
+
+    if !warned.is_empty() {
+        println!(
+            "{} files have calls that may be to overloaded methods:",
+            warned.len()
+        );
+
+        for (path, entry) in warned {
+            println!("  {}", path.display());
+
+            for warning in &entry.warnings {
+                println!("    {}", warning);
+            }
+        }
+    }
+
+    let failed = manifest
+        .files
+        .iter()
+        .filter(|(_, entry)| entry.status == Status::Failed)
+        .collect::<Vec<_>>();
+
+    if !failed.is_empty() {
+        println!("{} files failed:", failed.len());
+
+        for (path, entry) in failed {
+            println!("  {}", path.display());
+
+            for line in entry.error.iter().flat_map(|error| error.lines()) {
+                println!("    {}", line);
+            }
+        }
+    }
+
+    println!("{} tokens used", total_usage.total_tokens);
+
+    let left = files.len() + waves.map(|wave| wave.len()).sum::<usize>();
+
+    if left > 0 {
+        println!(
+            "Budget exhausted, {} files left pending; continue with --resume",
+            left
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints the tokens each file would take and what converting them all would cost.
+fn estimate(converter: &Converter, files: &[SourceFile], pricing: &Pricing) -> Result<()> {
+    let mut prompt_tokens = 0;
+    let mut completion_tokens = 0;
+    let mut too_large = Vec::new();
+
+    println!("{:>10} {:>10}  File", "Prompt", "Completion");
+
+    for file in files {
+        let estimate = converter
+            .estimate(file)
+            .wrap_err_with(|| eyre!("{}", file.path.display()))?;
+
+        println!(
+            "{:>10} {:>10}  {}",
+            estimate.prompt_tokens,
+            estimate.completion_tokens,
+            file.relative_path.display()
+        );
+
+        prompt_tokens += estimate.prompt_tokens;
+        completion_tokens += estimate.completion_tokens;
+
+        if !estimate.fits {
+            too_large.push(&file.relative_path);
+        }
+    }
+
+    println!("{:>10} {:>10}  Total", prompt_tokens, completion_tokens);
+
+    if !too_large.is_empty() {
+        println!(
+            "\nExceeding the context window of {} tokens, to be converted in chunks:",
+            converter.context_size
+        );
+
+        for path in too_large {
+            println!("  {}", path.display());
+        }
+    }
+
+    println!("\nEstimated cost:");
+
+    let model = converter.backend.model();
+    let selected = pricing.select(model);
+
+    if selected.is_none() {
+        println!("  {:<24} unknown", model);
+    }
+
+    for (name, price) in &pricing.0 {
+        println!(
+            "  {:<24} ${:.4}{}",
+            name,
+            price.cost(prompt_tokens, completion_tokens),
+            if selected == Some(name) {
+                " (selected)"
+            } else {
+                ""
+            }
+        );
+    }
+
     Ok(())
 }
+
+/// Maps the dependencies of the Maven and Gradle build files to Composer
+/// packages, printing which ones need to be replaced manually.
+fn requirements(source: &Path, mappings: &[Mapping], filter: &Filter) -> Result<Vec<Requirement>> {
+    let mut dependencies = Vec::new();
+
+    for path in build_files(source, filter)? {
+        dependencies.extend(dependencies::parse(path)?);
+    }
+
+    dependencies.sort_by_key(|dependency| dependency.to_string());
+    dependencies.dedup();
+
+    if dependencies.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut requirements = Vec::new();
+    let mut unmapped = Vec::new();
+
+    println!("\nDependencies:");
+
+    for dependency in dependencies {
+        match dependencies::find(mappings, &dependency) {
+            Some(Mapping {
+                package: Some((package, version)),
+                ..
+            }) => {
+                println!("  {} -> {} {}", dependency, package, version);
+
+                requirements.push(Requirement {
+                    package,
+                    version,
+                    dev: dependency.test,
+                });
+            }
+            Some(Mapping { package: None, .. }) => println!("  {} -> not needed", dependency),
+            None => unmapped.push(dependency),
+        }
+    }
+
+    if !unmapped.is_empty() {
+        println!("\nUnmapped dependencies, to be replaced manually:");
+
+        for dependency in unmapped {
+            println!("  {}", dependency);
+        }
+    }
+
+    Ok(requirements)
+}
@@ -0,0 +1,183 @@
+use color_eyre::{eyre::Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pending,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub status: Status,
+    /// Relative to the destination directory, like `split`
+    pub output: PathBuf,
+    /// SHA-256 of the source file
+    pub hash: String,
+    pub tokens: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Explanations the model gave besides the code
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// Files the output was split into, one per type, instead of `output`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub split: Vec<PathBuf>,
+    /// Calls to methods of the original name of overloads that could not be
+    /// checked, as `line:column: message`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+impl Entry {
+    /// Files the output was written to, under the destination directory.
+    pub fn outputs(&self, destination: &Path) -> Vec<PathBuf> {
+        let outputs = match self.split.is_empty() {
+            true => std::slice::from_ref(&self.output),
+            false => &self.split,
+        };
+
+        outputs.iter().map(|path| destination.join(path)).collect()
+    }
+}
+
+/// An output path as stored in the manifest, relative to the destination
+/// directory so that runs from another working directory can resume.
+pub fn relative(path: &Path, destination: &Path) -> PathBuf {
+    path.strip_prefix(destination).unwrap_or(path).to_owned()
+}
+
+/// State of a directory run, keyed by source path relative to the source
+/// directory and saved after every file so an interrupted run can resume.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub files: BTreeMap<PathBuf, Entry>,
+}
+
+impl Manifest {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        let content = fs::read_to_string(path).wrap_err_with(|| format!("{}", path.display()))?;
+
+        serde_json::from_str(&content).wrap_err_with(|| format!("{}", path.display()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&partial, path)?;
+
+        Ok(())
+    }
+
+    /// Whether a previous run already converted this exact source to the same
+    /// output, relative to the destination directory.
+    pub fn is_done(&self, file: &Path, hash: &str, output: &Path) -> bool {
+        self.files.get(file).is_some_and(|entry| {
+            entry.status == Status::Done && entry.hash == hash && entry.output == output
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(status: Status, output: &str) -> Entry {
+        Entry {
+            status,
+            output: PathBuf::from(output),
+            hash: "abc".to_owned(),
+            tokens: 100,
+            error: None,
+            notes: Vec::new(),
+            split: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn resume() {
+        let mut manifest = Manifest::default();
+        let file = Path::new("a/A.java");
+        manifest
+            .files
+            .insert(file.to_owned(), entry(Status::Done, "a/A.php"));
+
+        assert!(manifest.is_done(file, "abc", Path::new("a/A.php")));
+        assert!(!manifest.is_done(file, "def", Path::new("a/A.php")));
+        assert!(!manifest.is_done(file, "abc", Path::new("src/A/A.php")));
+        assert!(!manifest.is_done(Path::new("B.java"), "abc", Path::new("B.php")));
+
+        manifest
+            .files
+            .insert(file.to_owned(), entry(Status::Failed, "a/A.php"));
+        assert!(!manifest.is_done(file, "abc", Path::new("a/A.php")));
+    }
+
+    #[test]
+    fn outputs() {
+        let destination = Path::new("out");
+        assert_eq!(
+            relative(Path::new("out/a/A.php"), destination),
+            Path::new("a/A.php")
+        );
+        assert_eq!(
+            relative(Path::new("a/A.php"), destination),
+            Path::new("a/A.php")
+        );
+
+        let mut entry = entry(Status::Done, "a/A.php");
+        assert_eq!(entry.outputs(destination), [Path::new("out/a/A.php")]);
+
+        entry.split = vec![PathBuf::from("a/A.php"), PathBuf::from("a/B.php")];
+        assert_eq!(
+            entry.outputs(destination),
+            [Path::new("out/a/A.php"), Path::new("out/a/B.php")]
+        );
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("java-to-php-manifest-{}", std::process::id()));
+        let path = dir.join("state").join("manifest.json");
+
+        let mut manifest = Manifest::default();
+        let mut failed = entry(Status::Failed, "B.php");
+        failed.error = Some("Timeout".to_owned());
+        manifest
+            .files
+            .insert(PathBuf::from("A.java"), entry(Status::Done, "A.php"));
+        manifest.files.insert(PathBuf::from("B.java"), failed);
+        manifest.save(&path).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        let loaded = Manifest::load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Empty lists are left out
+        assert!(!content.contains("notes"));
+        assert!(content.contains("\"status\": \"failed\""));
+        assert_eq!(loaded.files.len(), 2);
+        assert!(loaded.is_done(Path::new("A.java"), "abc", Path::new("A.php")));
+        assert_eq!(
+            loaded.files[Path::new("B.java")].error.as_deref(),
+            Some("Timeout")
+        );
+        assert!(Manifest::load(&path).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use std::str::FromStr;
+
+/// Maps the packages starting with a prefix to a namespace, e.g. `com.acme=Acme`.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub package: String,
+    pub namespace: String,
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (package, namespace) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected PACKAGE=NAMESPACE, found `{}`", s))?;
+
+        Ok(Self {
+            package: package.trim().to_owned(),
+            namespace: namespace.trim().trim_matches('\\').to_owned(),
+        })
+    }
+}
+
+impl Rule {
+    /// The rest of the package if it starts with the prefix of the rule.
+    fn strip<'a>(&self, package: &'a str) -> Option<&'a str> {
+        if self.package.is_empty() {
+            return Some(package);
+        }
+
+        match package.strip_prefix(self.package.as_str())? {
+            "" => Some(""),
+            rest => rest.strip_prefix('.'),
+        }
+    }
+}
+
+/// `my_billing` becomes `MyBilling`.
+fn studly(segment: &str) -> String {
+    segment
+        .split('_')
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|first| first.to_uppercase().chain(chars))
+        })
+        .flatten()
+        .collect()
+}
+
+/// The namespace of a package, given by the rule with the longest matching
+/// prefix, with the remaining segments of the package in studly case.
+pub fn namespace(rules: &[Rule], package: &str) -> String {
+    let (prefix, rest) = rules
+        .iter()
+        .filter_map(|rule| Some((rule, rule.strip(package)?)))
+        .max_by_key(|(rule, _)| rule.package.len())
+        .map_or(("", package), |(rule, rest)| {
+            (rule.namespace.as_str(), rest)
+        });
+
+    prefix
+        .split('\\')
+        .map(str::to_owned)
+        .chain(rest.split('.').map(studly))
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules() {
+        let rule = "com.acme = \\Acme\\".parse::<Rule>().unwrap();
+        assert_eq!(rule.package, "com.acme");
+        assert_eq!(rule.namespace, "Acme");
+
+        assert!("com.acme".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn namespaces() {
+        let rules = ["com.acme=Acme", "com.acme.billing=Billing", "=Vendor"]
+            .map(|rule| rule.parse::<Rule>().unwrap());
+
+        assert_eq!(namespace(&rules, "com.acme.shop_cart"), "Acme\\ShopCart");
+        assert_eq!(namespace(&rules, "com.acme.billing.tax"), "Billing\\Tax");
+        assert_eq!(namespace(&rules, "com.acme"), "Acme");
+        assert_eq!(namespace(&rules, "com.acmeco"), "Vendor\\Com\\Acmeco");
+        assert_eq!(namespace(&rules, ""), "Vendor");
+        assert_eq!(namespace(&[], "org.example"), "Org\\Example");
+    }
+}
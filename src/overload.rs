@@ -0,0 +1,561 @@
+use crate::php::{self, SyntaxError, Token, TokenKind};
+use clap::ValueEnum;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How overloaded Java methods are declared in PHP, which has no overloading.
+#[derive(ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Each overload is renamed after the types of its parameters, callers included
+    #[default]
+    Suffix,
+    /// A variadic method keeps the name and dispatches to the renamed overloads
+    Dispatch,
+}
+
+/// PHP name of an overloaded Java method or constructor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    /// Java declaration, e.g. `Invoice.add(int, int)`
+    pub method: String,
+    /// Java method name, `__construct` for constructors
+    pub original: String,
+    pub name: String,
+}
+
+impl Rename {
+    pub fn new(owner: &str, method: Option<&str>, params: &[String], strategy: Strategy) -> Self {
+        let java = format!(
+            "{}{}({})",
+            owner,
+            method.map_or(String::new(), |method| format!(".{}", method)),
+            params.join(", ")
+        );
+
+        // Constructors are always dispatched, as PHP only has one
+        let (original, prefix, strategy) = match method {
+            Some(method) => (method, method, strategy),
+            None => ("__construct", "construct", Strategy::Dispatch),
+        };
+
+        let mut name = prefix.to_owned();
+
+        for ty in params {
+            name.push_str(&suffix(ty));
+        }
+
+        // The dispatcher already takes the name
+        if params.is_empty() && strategy == Strategy::Dispatch {
+            name.push_str("NoArgs");
+        }
+
+        Self {
+            method: java,
+            original: original.to_owned(),
+            name,
+        }
+    }
+
+    /// Java type and method name, e.g. `Invoice.add`.
+    fn owner(&self) -> &str {
+        self.method.split('(').next().unwrap_or_default()
+    }
+
+    pub fn is_constructor(&self) -> bool {
+        self.original == "__construct"
+    }
+}
+
+/// StudlyCase name of a Java type, e.g. `IntArray` for `int[]` and `Map` for
+/// `java.util.Map<K, V>`.
+fn suffix(ty: &str) -> String {
+    let dimensions = ty.matches("[]").count() + ty.matches("...").count();
+    let base = ty.split(['<', '[']).next().unwrap_or_default();
+    let base = base
+        .trim_end_matches("...")
+        .rsplit('.')
+        .next()
+        .unwrap_or_default();
+    let mut chars = base.trim().chars();
+
+    let mut suffix = chars
+        .next()
+        .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+        .unwrap_or_default();
+
+    suffix.push_str(&"Array".repeat(dimensions));
+    suffix
+}
+
+/// Explains how the overloads are declared and called.
+pub fn instructions(strategy: Strategy, renames: &[&Rename]) -> String {
+    let constructors = renames.iter().any(|rename| rename.is_constructor());
+    let methods = renames.iter().any(|rename| !rename.is_constructor());
+
+    let mut instructions = "PHP has no overloading.".to_owned();
+
+    if methods {
+        instructions.push_str(match strategy {
+            Strategy::Suffix => {
+                " Overloaded methods are renamed as listed, both where they are declared \
+                and where they are called."
+            }
+            Strategy::Dispatch => {
+                " Overloaded methods are declared as protected methods named as listed, \
+                and classes declaring several overloads of a method also declare a method \
+                of the original name taking `...$args` and dispatching to them on the \
+                number and types of its arguments."
+            }
+        });
+    }
+
+    if constructors {
+        instructions.push_str(
+            " Overloaded constructors are merged into a single `__construct(...$args)` \
+            dispatching on the number and types of its arguments to protected methods named \
+            as listed.",
+        );
+    }
+
+    for rename in renames {
+        instructions.push_str(&format!("\n`{}`: `{}`", rename.method, rename.name));
+    }
+
+    instructions
+}
+
+/// Object or class a method is called on.
+enum Receiver<'a> {
+    /// `$this`, `self`, `static` or `parent`
+    Own,
+    /// Type a variable or property is declared as or assigned an instance of
+    Type(&'a str),
+    Unknown,
+}
+
+const MODIFIERS: &[&str] = &[
+    "public",
+    "protected",
+    "private",
+    "readonly",
+    "static",
+    "var",
+];
+
+/// Types of the variables and properties by name without `$`, as declared or
+/// assigned an instance, or none when they differ.
+fn types<'a>(code: &'a str, tokens: &[Token]) -> BTreeMap<&'a str, Option<&'a str>> {
+    let text = |i: usize| &code[tokens[i].start..tokens[i].end];
+    let punct = |i: usize, c: char| tokens.get(i).is_some_and(|t| t.kind == TokenKind::Punct(c));
+    let mut types = BTreeMap::new();
+
+    let mut bind = |name: &'a str, ty: &'a str| {
+        types
+            .entry(name.trim_start_matches('$'))
+            .and_modify(|known: &mut Option<&str>| {
+                if *known != Some(ty) {
+                    *known = None;
+                }
+            })
+            .or_insert(Some(ty));
+    };
+
+    for i in 1..tokens.len().saturating_sub(1) {
+        // Parameters, promoted or not, caught exceptions and typed properties
+        let declared = tokens[i].kind == TokenKind::Ident
+            && tokens[i + 1].kind == TokenKind::Variable
+            && (['(', ',', '?', '|', '&', '\\']
+                .iter()
+                .any(|&c| punct(i - 1, c))
+                || MODIFIERS.contains(&text(i - 1).to_ascii_lowercase().as_str()));
+
+        if declared {
+            bind(text(i + 1), text(i));
+        }
+
+        // `$x = new X` and `$this->x = new X`, with `X` possibly qualified
+        let target = match tokens[i].kind {
+            TokenKind::Variable => true,
+            TokenKind::Ident => {
+                i >= 3
+                    && punct(i - 2, '-')
+                    && punct(i - 1, '>')
+                    && text(i - 3).eq_ignore_ascii_case("$this")
+            }
+            _ => false,
+        };
+
+        let assigned = target
+            && punct(i + 1, '=')
+            && !punct(i + 2, '=')
+            && i + 2 < tokens.len()
+            && text(i + 2).eq_ignore_ascii_case("new");
+
+        if !assigned {
+            continue;
+        }
+
+        let mut j = i + 3;
+
+        while punct(j, '\\') || punct(j + 1, '\\') {
+            j += 1;
+        }
+
+        if tokens.get(j).is_some_and(|t| t.kind == TokenKind::Ident) {
+            bind(text(i), text(j));
+        }
+    }
+
+    types
+}
+
+/// Method calls by index of the token naming the method, with their receivers.
+fn calls<'a>(code: &'a str, tokens: &[Token]) -> Vec<(usize, Receiver<'a>)> {
+    let text = |i: usize| &code[tokens[i].start..tokens[i].end];
+    let punct = |i: usize, c: char| tokens.get(i).is_some_and(|t| t.kind == TokenKind::Punct(c));
+    let types = types(code, tokens);
+
+    let typed = |name: &str| match types.get(name.trim_start_matches('$')) {
+        Some(Some(ty)) => Receiver::Type(ty),
+        _ => Receiver::Unknown,
+    };
+
+    let mut calls = Vec::new();
+
+    for i in 3..tokens.len().saturating_sub(1) {
+        if tokens[i].kind != TokenKind::Ident || !punct(i + 1, '(') {
+            continue;
+        }
+
+        let receiver = if punct(i - 2, '-') && punct(i - 1, '>') {
+            // Nullsafe calls too
+            let j = if punct(i - 3, '?') { i - 4 } else { i - 3 };
+
+            match tokens[j].kind {
+                TokenKind::Variable if text(j).eq_ignore_ascii_case("$this") => Receiver::Own,
+                TokenKind::Variable => typed(text(j)),
+                TokenKind::Ident
+                    if j >= 3
+                        && punct(j - 2, '-')
+                        && punct(j - 1, '>')
+                        && text(j - 3).eq_ignore_ascii_case("$this") =>
+                {
+                    typed(text(j))
+                }
+                _ => Receiver::Unknown,
+            }
+        } else if punct(i - 2, ':') && punct(i - 1, ':') {
+            match (
+                tokens[i - 3].kind,
+                text(i - 3).to_ascii_lowercase().as_str(),
+            ) {
+                (TokenKind::Ident, "self" | "static" | "parent") => Receiver::Own,
+                (TokenKind::Ident, _) => Receiver::Type(text(i - 3)),
+                (TokenKind::Variable, _) => typed(text(i - 3)),
+                _ => Receiver::Unknown,
+            }
+        } else {
+            continue;
+        };
+
+        calls.push((i, receiver));
+    }
+
+    calls
+}
+
+/// PHP names of the overloads of a method, e.g. `` `addInt` or `addString` ``.
+fn names<'a>(renames: impl IntoIterator<Item = &'a Rename>) -> String {
+    let mut names = Vec::new();
+
+    for rename in renames {
+        let name = format!("`{}`", rename.name);
+
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    names.join(" or ")
+}
+
+/// Errors for overloads missing from the classes of a converted file, given the
+/// overloads it declares, for its calls to the original names of methods it
+/// does not declare itself, and for calls to them on objects of a known type,
+/// whose overloads are given by type name, if it names a single type.
+pub fn check<'a>(
+    code: &str,
+    strategy: Strategy,
+    declared: &[&Rename],
+    called: &[&Rename],
+    methods: impl Fn(&str) -> Option<&'a [Rename]>,
+) -> Vec<SyntaxError> {
+    let Ok(tokens) = php::lex(code) else {
+        return Vec::new();
+    };
+
+    let text = |i: usize| &code[tokens[i].start..tokens[i].end];
+
+    let functions = tokens
+        .windows(2)
+        .filter(|pair| {
+            pair[1].kind == TokenKind::Ident
+                && code[pair[0].start..pair[0].end].eq_ignore_ascii_case("function")
+        })
+        .map(|pair| code[pair[1].start..pair[1].end].to_ascii_lowercase())
+        .collect::<BTreeSet<_>>();
+
+    let mut errors = Vec::new();
+
+    let mut missing = Vec::new();
+
+    for rename in declared {
+        missing.push((&rename.name, &rename.method));
+
+        // Dispatchers are declared along with several overloads, not to override one
+        let overloads = declared
+            .iter()
+            .filter(|other| other.owner() == rename.owner())
+            .count();
+
+        if (strategy == Strategy::Dispatch || rename.is_constructor()) && overloads > 1 {
+            missing.push((&rename.original, &rename.method));
+        }
+    }
+
+    let mut reported = BTreeSet::new();
+
+    for (name, method) in missing {
+        let name_lowercase = name.to_ascii_lowercase();
+
+        if !functions.contains(&name_lowercase) && reported.insert(name_lowercase) {
+            errors.push(SyntaxError::new(
+                code,
+                0,
+                format!("Missing method `{}` for `{}`", name, method),
+            ));
+        }
+    }
+
+    if strategy == Strategy::Dispatch {
+        return errors;
+    }
+
+    let mut renamed = BTreeMap::<String, Vec<&Rename>>::new();
+
+    for rename in declared.iter().chain(called) {
+        if !rename.is_constructor() && !functions.contains(&rename.original.to_ascii_lowercase()) {
+            renamed
+                .entry(rename.original.to_ascii_lowercase())
+                .or_default()
+                .push(rename);
+        }
+    }
+
+    for (i, receiver) in calls(code, &tokens) {
+        let name = text(i);
+
+        let (method, overloads) = match receiver {
+            Receiver::Own => match renamed.get(&name.to_ascii_lowercase()) {
+                Some(renames) => (name.to_owned(), names(renames.iter().copied())),
+                None => continue,
+            },
+            Receiver::Type(ty) => {
+                let Some(renames) = methods(ty) else {
+                    continue;
+                };
+
+                let renames = renames
+                    .iter()
+                    .filter(|rename| rename.original.eq_ignore_ascii_case(name))
+                    .collect::<Vec<_>>();
+
+                if renames.is_empty() {
+                    continue;
+                }
+
+                (format!("{}::{}", ty, name), names(renames))
+            }
+            Receiver::Unknown => continue,
+        };
+
+        errors.push(SyntaxError::new(
+            code,
+            tokens[i].start,
+            format!(
+                "Call to the overloaded method `{}`, renamed to {}",
+                method, overloads
+            ),
+        ));
+    }
+
+    errors
+}
+
+/// Calls to the original names of overloaded methods the file declares or may
+/// call, on objects of an unknown type or of a type name shared by several
+/// types, which [`check`] cannot tell apart from calls to methods of the same
+/// name of other types.
+pub fn unchecked<'a>(
+    code: &str,
+    strategy: Strategy,
+    declared: &[&Rename],
+    called: &[&Rename],
+    methods: impl Fn(&str) -> Option<&'a [Rename]>,
+) -> Vec<SyntaxError> {
+    let Ok(tokens) = php::lex(code) else {
+        return Vec::new();
+    };
+
+    if strategy == Strategy::Dispatch {
+        return Vec::new();
+    }
+
+    let mut warnings = Vec::new();
+
+    for (i, receiver) in calls(code, &tokens) {
+        let name = &code[tokens[i].start..tokens[i].end];
+
+        let renames = declared
+            .iter()
+            .chain(called)
+            .copied()
+            .filter(|rename| !rename.is_constructor() && rename.original.eq_ignore_ascii_case(name))
+            .collect::<Vec<_>>();
+
+        let receiver = match receiver {
+            Receiver::Unknown => "an object of unknown type".to_owned(),
+            Receiver::Type(ty) if methods(ty).is_none() => {
+                format!("`{}`, which may name several classes", ty)
+            }
+            _ => continue,
+        };
+
+        if !renames.is_empty() {
+            warnings.push(SyntaxError::new(
+                code,
+                tokens[i].start,
+                format!(
+                    "Call to `{}` on {}, which should be {} if it calls the overloaded method",
+                    name,
+                    receiver,
+                    names(renames)
+                ),
+            ));
+        }
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename(method: &str, params: &[&str], strategy: Strategy) -> Rename {
+        let params = params
+            .iter()
+            .map(|param| param.to_string())
+            .collect::<Vec<_>>();
+        Rename::new("Invoice", Some(method), &params, strategy)
+    }
+
+    fn messages(errors: Vec<SyntaxError>) -> Vec<String> {
+        errors.into_iter().map(|error| error.message).collect()
+    }
+
+    #[test]
+    fn names_after_parameter_types() {
+        let add = rename("add", &["int", "java.util.Map<K, V>"], Strategy::Suffix);
+        assert_eq!(add.method, "Invoice.add(int, java.util.Map<K, V>)");
+        assert_eq!(add.original, "add");
+        assert_eq!(add.name, "addIntMap");
+
+        let add = rename("add", &["String[][]", "long..."], Strategy::Suffix);
+        assert_eq!(add.name, "addStringArrayArrayLongArray");
+
+        assert_eq!(rename("add", &[], Strategy::Suffix).name, "add");
+        assert_eq!(rename("add", &[], Strategy::Dispatch).name, "addNoArgs");
+
+        let construct = Rename::new("Invoice", None, &["int".to_owned()], Strategy::Suffix);
+        assert_eq!(construct.method, "Invoice(int)");
+        assert_eq!(construct.name, "constructInt");
+        assert!(construct.is_constructor());
+    }
+
+    #[test]
+    fn missing_overloads() {
+        let (int, string) = (
+            rename("add", &["int"], Strategy::Suffix),
+            rename("add", &["String"], Strategy::Suffix),
+        );
+
+        let code = "<?php\nclass Invoice {\n    public function addInt(int $a) {}\n}\n";
+        let errors = check(code, Strategy::Suffix, &[&int, &string], &[], |_| Some(&[]));
+        assert_eq!(
+            messages(errors),
+            ["Missing method `addString` for `Invoice.add(String)`"]
+        );
+
+        let code = "<?php\nclass Invoice {\n    public function addInt(int $a) {}\n    \
+            protected function addString(string $a) {}\n}\n";
+        let errors = check(code, Strategy::Dispatch, &[&int, &string], &[], |_| {
+            Some(&[])
+        });
+        assert_eq!(
+            messages(errors),
+            ["Missing method `add` for `Invoice.add(int)`"]
+        );
+    }
+
+    #[test]
+    fn calls_to_original_names() {
+        let renames = [
+            rename("add", &["int"], Strategy::Suffix),
+            rename("add", &["String"], Strategy::Suffix),
+        ];
+
+        let called = renames.iter().collect::<Vec<_>>();
+
+        let methods = |ty: &str| match ty {
+            "Invoice" => Some(renames.as_slice()),
+            "Order" => None,
+            _ => Some(&[][..]),
+        };
+
+        let code =
+            "<?php\nclass Basket {\n    public function fill(Invoice $invoice, Order $order, \
+            Cart $cart) {\n        $invoice->add(1);\n        $order->add(1);\n        \
+            $cart->add(1);\n        $unknown->add(1);\n        $this->add(1);\n    }\n}\n";
+
+        let errors = check(code, Strategy::Suffix, &[], &called, methods);
+        assert_eq!(
+            messages(errors),
+            [
+                "Call to the overloaded method `Invoice::add`, renamed to `addInt` or `addString`",
+                "Call to the overloaded method `add`, renamed to `addInt` or `addString`",
+            ]
+        );
+
+        let warnings = unchecked(code, Strategy::Suffix, &[], &called, methods);
+        assert_eq!(
+            messages(warnings),
+            [
+                "Call to `add` on `Order`, which may name several classes, which should be \
+                `addInt` or `addString` if it calls the overloaded method",
+                "Call to `add` on an object of unknown type, which should be `addInt` or \
+                `addString` if it calls the overloaded method",
+            ]
+        );
+
+        // Classes declaring a method of the original name
+        let code = "<?php\nclass Basket {\n    public function add($a) {}\n    \
+            public function fill() {\n        $this->add(1);\n    }\n}\n";
+        assert!(check(code, Strategy::Suffix, &[], &called, methods).is_empty());
+
+        // Dispatchers keep the original name
+        let code = "<?php\nclass Basket {\n    public function fill(Invoice $invoice) {\n        \
+            $invoice->add(1);\n        $unknown->add(1);\n    }\n}\n";
+        assert!(check(code, Strategy::Dispatch, &[], &called, methods).is_empty());
+        assert!(unchecked(code, Strategy::Dispatch, &[], &called, methods).is_empty());
+    }
+}
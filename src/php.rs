@@ -0,0 +1,2716 @@
+use clap::ValueEnum;
+use color_eyre::Result;
+use std::{fmt, ops::Range};
+
+#[derive(ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Version {
+    #[value(name = "7.4")]
+    V7_4,
+    #[value(name = "8.0")]
+    V8_0,
+    #[default]
+    #[value(name = "8.1")]
+    V8_1,
+    #[value(name = "8.2")]
+    V8_2,
+    #[value(name = "8.3")]
+    V8_3,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::V7_4 => "7.4",
+            Self::V8_0 => "8.0",
+            Self::V8_1 => "8.1",
+            Self::V8_2 => "8.2",
+            Self::V8_3 => "8.3",
+        })
+    }
+}
+
+/// Language features by the version introducing them. Attributes alone on
+/// their line are left out, as older versions read them as comments.
+pub const FEATURES: &[(&str, Version)] = &[
+    ("Attribute", Version::V8_0),
+    ("Catch without a variable", Version::V8_0),
+    ("Constructor property promotion", Version::V8_0),
+    ("`match` expression", Version::V8_0),
+    ("`mixed` type", Version::V8_0),
+    ("Named argument", Version::V8_0),
+    ("Nullsafe operator `?->`", Version::V8_0),
+    ("`static` return type", Version::V8_0),
+    ("`new` with an arbitrary expression", Version::V8_0),
+    ("`throw` expression", Version::V8_0),
+    ("Union type", Version::V8_0),
+    ("Enum", Version::V8_1),
+    ("Explicit octal literal `0o`", Version::V8_1),
+    ("First-class callable syntax", Version::V8_1),
+    ("Intersection type", Version::V8_1),
+    ("`never` return type", Version::V8_1),
+    ("Readonly property", Version::V8_1),
+    ("Disjunctive normal form type", Version::V8_2),
+    ("Readonly class", Version::V8_2),
+    ("Typed class constant", Version::V8_3),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    InlineHtml,
+    OpenTag,
+    CloseTag,
+    Ident,
+    Variable,
+    Literal,
+    Punct(char),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl SyntaxError {
+    pub fn new(code: &str, offset: usize, message: String) -> Self {
+        let (line, column) = position(code, offset);
+
+        Self {
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+/// 1-based line and column of a byte offset.
+pub fn position(code: &str, offset: usize) -> (usize, usize) {
+    let before = &code[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |n| n + 1) + 1;
+    (line, column)
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || !c.is_ascii()
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || !c.is_ascii()
+}
+
+pub fn lex(code: &str) -> Result<Vec<Token>, SyntaxError> {
+    let bytes = code.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut in_php = false;
+
+    let unterminated =
+        |what: &str, start: usize| SyntaxError::new(code, start, format!("Unterminated {}", what));
+
+    while i < bytes.len() {
+        let start = i;
+        let rest = &code[i..];
+
+        if !in_php {
+            let open = rest.find("<?").map_or(bytes.len(), |n| i + n);
+
+            if open > i {
+                tokens.push(Token {
+                    kind: TokenKind::InlineHtml,
+                    start,
+                    end: open,
+                });
+            }
+
+            if open == bytes.len() {
+                break;
+            }
+
+            i = open + 2;
+
+            if code[i..].starts_with("php") {
+                i += 3;
+            } else if code[i..].starts_with('=') {
+                i += 1;
+            }
+
+            tokens.push(Token {
+                kind: TokenKind::OpenTag,
+                start: open,
+                end: i,
+            });
+
+            in_php = true;
+            continue;
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+
+        if c.is_whitespace() {
+            i += c.len_utf8();
+        } else if rest.starts_with("?>") {
+            i += 2;
+
+            if code[i..].starts_with('\n') {
+                i += 1;
+            }
+
+            tokens.push(Token {
+                kind: TokenKind::CloseTag,
+                start,
+                end: i,
+            });
+
+            in_php = false;
+        } else if rest.starts_with("//") || c == '#' && !rest.starts_with("#[") {
+            let line_end = rest.find('\n').unwrap_or(rest.len());
+            let tag = rest[..line_end].find("?>").unwrap_or(line_end);
+            i += tag;
+        } else if rest.starts_with("/*") {
+            i = code[i + 2..]
+                .find("*/")
+                .map(|n| i + 2 + n + 2)
+                .ok_or_else(|| unterminated("comment", start))?;
+        } else if rest.starts_with("<<<") {
+            let header_end = rest
+                .find('\n')
+                .ok_or_else(|| unterminated("heredoc", start))?;
+            let label = rest[3..header_end]
+                .trim()
+                .trim_matches(|c| c == '"' || c == '\'');
+
+            if label.is_empty() || !label.chars().all(is_ident_char) {
+                return Err(unterminated("heredoc", start));
+            }
+
+            let mut line_start = i + header_end + 1;
+
+            i = loop {
+                if line_start >= bytes.len() {
+                    return Err(unterminated("heredoc", start));
+                }
+
+                let line = &code[line_start..];
+                let trimmed = line.trim_start_matches([' ', '\t']);
+                let indent = line.len() - trimmed.len();
+
+                if trimmed.starts_with(label) && !trimmed[label.len()..].starts_with(is_ident_char)
+                {
+                    break line_start + indent + label.len();
+                }
+
+                line_start = line.find('\n').map_or(bytes.len(), |n| line_start + n + 1);
+            };
+
+            tokens.push(Token {
+                kind: TokenKind::Literal,
+                start,
+                end: i,
+            });
+        } else if c == '"' || c == '\'' || c == '`' {
+            i += 1;
+
+            loop {
+                match bytes.get(i) {
+                    None => return Err(unterminated("string", start)),
+                    Some(b'\\') => i += 2,
+                    Some(&b) if b == c as u8 => break i += 1,
+                    Some(_) => i += 1,
+                }
+            }
+
+            tokens.push(Token {
+                kind: TokenKind::Literal,
+                start,
+                end: i,
+            });
+        } else if c.is_ascii_digit()
+            || c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            while let Some(&b) = bytes.get(i) {
+                let exponent_sign = (b == b'+' || b == b'-')
+                    && matches!(bytes[i - 1], b'e' | b'E')
+                    && !code[start..].starts_with("0x");
+
+                if b.is_ascii_alphanumeric() || b == b'_' || b == b'.' || exponent_sign {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+
+            tokens.push(Token {
+                kind: TokenKind::Literal,
+                start,
+                end: i,
+            });
+        } else if c == '$' && rest[1..].starts_with(is_ident_start) {
+            i += 1;
+            i = code[i..]
+                .find(|c| !is_ident_char(c))
+                .map_or(bytes.len(), |n| i + n);
+
+            tokens.push(Token {
+                kind: TokenKind::Variable,
+                start,
+                end: i,
+            });
+        } else if is_ident_start(c) {
+            i = code[i..]
+                .find(|c| !is_ident_char(c))
+                .map_or(bytes.len(), |n| i + n);
+
+            tokens.push(Token {
+                kind: TokenKind::Ident,
+                start,
+                end: i,
+            });
+        } else {
+            i += c.len_utf8();
+
+            tokens.push(Token {
+                kind: TokenKind::Punct(c),
+                start,
+                end: i,
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+const TYPE_KEYWORDS: &[&str] = &["class", "interface", "trait", "enum"];
+
+/// Byte offset of the closing brace of the type declaration named `name`.
+pub fn type_body_end(code: &str, name: &str) -> Result<Option<usize>> {
+    let tokens = lex(code)?;
+    let text = |token: &Token| &code[token.start..token.end];
+
+    let declaration = tokens.windows(2).enumerate().position(|(i, pair)| {
+        let is_constant = i > 0 && tokens[i - 1].kind == TokenKind::Punct(':');
+
+        !is_constant
+            && pair[0].kind == TokenKind::Ident
+            && TYPE_KEYWORDS.contains(&text(&pair[0]).to_ascii_lowercase().as_str())
+            && text(&pair[1]) == name
+    });
+
+    let Some(declaration) = declaration else {
+        return Ok(None);
+    };
+
+    let mut depth = 0;
+
+    for token in &tokens[declaration..] {
+        match token.kind {
+            TokenKind::Punct('{') => depth += 1,
+            TokenKind::Punct('}') => {
+                depth -= 1;
+
+                if depth == 0 {
+                    return Ok(Some(token.start));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(None)
+}
+
+/// Names and spans of the top-level type declarations, each starting where the
+/// previous statement ends so that leading comments and attributes are included.
+pub fn type_declarations(code: &str) -> Result<Vec<(String, Range<usize>)>, SyntaxError> {
+    let tokens = lex(code)?;
+    let text = |token: &Token| &code[token.start..token.end];
+    let mut declarations = Vec::new();
+    let mut depth = 0;
+    let mut statement_end = 0;
+    let mut declaration = None;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::OpenTag if depth == 0 => statement_end = token.end,
+            TokenKind::Punct(';') if depth == 0 => statement_end = token.end,
+            TokenKind::Punct('{') => depth += 1,
+            TokenKind::Punct('}') => {
+                depth -= 1;
+
+                if depth == 0 {
+                    if let Some(name) = declaration.take() {
+                        declarations.push((name, statement_end..token.end));
+                    }
+
+                    statement_end = token.end;
+                }
+            }
+            TokenKind::Ident if depth == 0 && declaration.is_none() => {
+                // `Foo::class`, `$foo->class` and `new class`
+                let after_operator = i > 0
+                    && (matches!(tokens[i - 1].kind, TokenKind::Punct(':' | '>'))
+                        || text(&tokens[i - 1]).eq_ignore_ascii_case("new"));
+
+                let name = tokens
+                    .get(i + 1)
+                    .filter(|next| next.kind == TokenKind::Ident);
+
+                if let (Some(name), false) = (name, after_operator) {
+                    if TYPE_KEYWORDS.contains(&text(token).to_ascii_lowercase().as_str()) {
+                        declaration = Some(text(name).to_owned());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(declarations)
+}
+
+/// The code with the bodies of functions and methods left out, for the
+/// signatures of the types it declares.
+pub fn signatures(code: &str) -> Result<String, SyntaxError> {
+    let tokens = lex(code)?;
+    let mut signatures = String::new();
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = tokens[i];
+        i += 1;
+
+        if token.kind != TokenKind::Ident
+            || !code[token.start..token.end].eq_ignore_ascii_case("function")
+        {
+            continue;
+        }
+
+        let mut depth = 0;
+
+        while let Some(token) = tokens.get(i) {
+            match token.kind {
+                TokenKind::Punct('(') => depth += 1,
+                TokenKind::Punct(')') => depth -= 1,
+                TokenKind::Punct(';') if depth == 0 => break,
+                TokenKind::Punct('{') if depth == 0 => {
+                    signatures.push_str(&code[copied..tokens[i - 1].end]);
+                    signatures.push(';');
+                    copied = code.len();
+
+                    // Closures within the body go along with it
+                    while let Some(token) = tokens.get(i) {
+                        match token.kind {
+                            TokenKind::Punct('{') => depth += 1,
+                            TokenKind::Punct('}') => depth -= 1,
+                            _ => {}
+                        }
+
+                        i += 1;
+
+                        if depth == 0 {
+                            copied = token.end;
+                            break;
+                        }
+                    }
+
+                    break;
+                }
+                _ => {}
+            }
+
+            i += 1;
+        }
+    }
+
+    signatures.push_str(&code[copied..]);
+    Ok(signatures)
+}
+
+/// Words that may be followed by another identifier, unlike the types of
+/// Java-style declarations such as `int count = 0;`.
+const KEYWORDS: &[&str] = &[
+    "abstract",
+    "and",
+    "as",
+    "case",
+    "catch",
+    "class",
+    "clone",
+    "const",
+    "echo",
+    "else",
+    "elseif",
+    "enum",
+    "extends",
+    "final",
+    "fn",
+    "function",
+    "global",
+    "goto",
+    "implements",
+    "include",
+    "include_once",
+    "instanceof",
+    "insteadof",
+    "interface",
+    "namespace",
+    "new",
+    "or",
+    "print",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "require",
+    "require_once",
+    "return",
+    "static",
+    "throw",
+    "trait",
+    "use",
+    "var",
+    "xor",
+    "yield",
+];
+
+/// Reserved words that cannot start an expression.
+const STATEMENT_KEYWORDS: &[&str] = &[
+    "abstract",
+    "and",
+    "as",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "declare",
+    "default",
+    "do",
+    "echo",
+    "else",
+    "elseif",
+    "enddeclare",
+    "endfor",
+    "endforeach",
+    "endif",
+    "endswitch",
+    "endwhile",
+    "extends",
+    "final",
+    "finally",
+    "for",
+    "foreach",
+    "global",
+    "goto",
+    "if",
+    "implements",
+    "insteadof",
+    "interface",
+    "or",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "switch",
+    "trait",
+    "try",
+    "use",
+    "var",
+    "while",
+    "xor",
+];
+
+const CASTS: &[&str] = &[
+    "array", "binary", "bool", "boolean", "double", "float", "int", "integer", "object", "real",
+    "string", "unset",
+];
+
+const MEMBER_MODIFIERS: &[&str] = &[
+    "abstract",
+    "final",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "static",
+    "var",
+];
+
+/// Operators made of several punctuation tokens, longest first.
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "**=", "??=", "...", "<=>", "===", "!==", "?->", "->", "=>", "::", "++", "--",
+    "==", "!=", "<>", "<=", ">=", "&&", "||", "??", "+=", "-=", "*=", "/=", ".=", "%=", "&=", "|=",
+    "^=", "<<", ">>", "**",
+];
+
+const ASSIGNMENTS: &[&str] = &[
+    "=", "+=", "-=", "*=", "/=", ".=", "%=", "**=", "??=", "&=", "|=", "^=", "<<=", ">>=",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Associativity {
+    Left,
+    Right,
+    None,
+}
+
+/// Precedence and associativity of a binary operator, given in lowercase.
+/// Assignments, which bind to the variable before them whatever the
+/// precedence, and the ternary operator are parsed apart.
+fn binary(op: &str) -> Option<(u8, Associativity)> {
+    use Associativity::*;
+
+    Some(match op {
+        "or" => (1, Left),
+        "xor" => (2, Left),
+        "and" => (3, Left),
+        "??" => (6, Right),
+        "||" => (7, Left),
+        "&&" => (8, Left),
+        "|" => (9, Left),
+        "^" => (10, Left),
+        "&" => (11, Left),
+        "==" | "!=" | "===" | "!==" | "<>" | "<=>" => (12, None),
+        "<" | "<=" | ">" | ">=" => (13, None),
+        "." => (14, Left),
+        "<<" | ">>" => (15, Left),
+        "+" | "-" => (16, Left),
+        "*" | "/" | "%" => (17, Left),
+        "instanceof" => (19, None),
+        "**" => (21, Right),
+        _ => return Option::None,
+    })
+}
+
+const ASSIGNMENT: u8 = 4;
+const TERNARY: u8 = 5;
+const NOT: u8 = 18;
+const UNARY: u8 = 20;
+
+/// What an expression is, as far as assigning to it goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expr {
+    /// A variable, property or array element
+    Variable,
+    /// An array literal, which can be destructured
+    Array,
+    Other,
+}
+
+type Parsed<T = ()> = Result<T, SyntaxError>;
+
+/// Recursive descent parser of PHP statements and expressions, stopping at the
+/// first syntax error.
+struct Parser<'a> {
+    code: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn kind(&self, n: usize) -> Option<TokenKind> {
+        self.tokens.get(self.pos + n).map(|token| token.kind)
+    }
+
+    fn text(&self, n: usize) -> &'a str {
+        self.tokens
+            .get(self.pos + n)
+            .map_or("", |token| &self.code[token.start..token.end])
+    }
+
+    /// The operator `n` tokens ahead, made of adjacent punctuation tokens.
+    fn op_at(&self, n: usize) -> &'a str {
+        let Some(first) = self.tokens.get(self.pos + n) else {
+            return "";
+        };
+
+        if !matches!(first.kind, TokenKind::Punct(_)) {
+            return "";
+        }
+
+        let rest = &self.code[first.start..];
+        let tokens = &self.tokens[self.pos + n..];
+
+        OPERATORS
+            .iter()
+            .find(|op| {
+                rest.starts_with(*op)
+                    && tokens.len() >= op.len()
+                    && tokens[..op.len()]
+                        .iter()
+                        .all(|token| matches!(token.kind, TokenKind::Punct(_)))
+            })
+            .map_or(&rest[..first.end - first.start], |op| &rest[..op.len()])
+    }
+
+    fn op(&self) -> &'a str {
+        self.op_at(0)
+    }
+
+    fn is_word_at(&self, n: usize, word: &str) -> bool {
+        self.kind(n) == Some(TokenKind::Ident) && self.text(n).eq_ignore_ascii_case(word)
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        self.is_word_at(0, word)
+    }
+
+    /// The identifier here in lowercase, or nothing.
+    fn word(&self) -> String {
+        match self.kind(0) {
+            Some(TokenKind::Ident) => self.text(0).to_ascii_lowercase(),
+            _ => String::new(),
+        }
+    }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        let found = self.op() == op;
+
+        if found {
+            self.pos += op.len();
+        }
+
+        found
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let found = self.is_word(word);
+
+        if found {
+            self.pos += 1;
+        }
+
+        found
+    }
+
+    fn found(&self) -> String {
+        let Some(token) = self.peek() else {
+            return "end of file".to_owned();
+        };
+
+        let text = match token.kind {
+            TokenKind::Punct(_) => self.op(),
+            _ => self.code[token.start..token.end]
+                .lines()
+                .next()
+                .unwrap_or_default(),
+        };
+
+        match text.chars().count() > 20 {
+            true => format!("`{}…`", text.chars().take(20).collect::<String>()),
+            false => format!("`{}`", text),
+        }
+    }
+
+    fn error(&self, message: String) -> SyntaxError {
+        let offset = self.peek().map_or(self.code.len(), |token| token.start);
+        SyntaxError::new(self.code, offset, message)
+    }
+
+    fn unexpected(&self, expected: &str) -> SyntaxError {
+        self.error(format!(
+            "Unexpected {}, expected {}",
+            self.found(),
+            expected
+        ))
+    }
+
+    /// Consumes an operator, returning its offset.
+    fn expect(&mut self, op: &str) -> Parsed<usize> {
+        let start = self.peek().map_or(self.code.len(), |token| token.start);
+
+        match self.eat_op(op) {
+            true => Ok(start),
+            false => Err(self.unexpected(&format!("`{}`", op))),
+        }
+    }
+
+    fn expect_word(&mut self, word: &str) -> Parsed {
+        match self.eat_word(word) {
+            true => Ok(()),
+            false => Err(self.unexpected(&format!("`{}`", word))),
+        }
+    }
+
+    /// Consumes the delimiter closing the one at `open`.
+    fn close(&mut self, close: &str, open: usize) -> Parsed {
+        if self.eat_op(close) {
+            return Ok(());
+        }
+
+        let (line, column) = position(self.code, open);
+
+        Err(self.error(format!(
+            "Expected `{}` to close `{}` at {}:{}, found {}",
+            close,
+            &self.code[open..open + 1],
+            line,
+            column,
+            self.found()
+        )))
+    }
+
+    fn identifier(&mut self) -> Parsed<&'a str> {
+        match self.kind(0) {
+            Some(TokenKind::Ident) => {
+                self.pos += 1;
+                Ok(self.text_before())
+            }
+            _ => Err(self.unexpected("a name")),
+        }
+    }
+
+    /// The text of the previous token.
+    fn text_before(&self) -> &'a str {
+        self.pos
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+            .map_or("", |token| &self.code[token.start..token.end])
+    }
+
+    fn variable(&mut self) -> Parsed {
+        match self.kind(0) {
+            Some(TokenKind::Variable) => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(TokenKind::Ident) if self.kind(1) == Some(TokenKind::Punct('(')) => Err(self
+                .error(format!(
+                    "Java-style method declaration `{} {}(`, methods are declared with `function`",
+                    self.text_before(),
+                    self.text(0)
+                ))),
+            Some(TokenKind::Ident) => Err(self.error(format!(
+                "Java-style declaration `{} {}`, variables need a `$`",
+                self.text_before(),
+                self.text(0)
+            ))),
+            _ => Err(self.unexpected("a variable")),
+        }
+    }
+
+    /// `;`, or `?>` which ends a statement as well.
+    fn end(&mut self) -> Parsed {
+        match self.kind(0) {
+            Some(TokenKind::Punct(';') | TokenKind::CloseTag) => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.unexpected("`;`")),
+        }
+    }
+
+    fn is_end(&self) -> bool {
+        matches!(
+            self.kind(0),
+            None | Some(TokenKind::Punct(';') | TokenKind::CloseTag)
+        )
+    }
+
+    fn file(mut self) -> Parsed {
+        self.statements(&[])?;
+
+        match self.peek() {
+            Some(_) => Err(self.unexpected("a statement")),
+            None => Ok(()),
+        }
+    }
+
+    /// Statements up to a closing brace, the end of the file or one of some
+    /// keywords ending the blocks of the alternative syntax.
+    fn statements(&mut self, ends: &[&str]) -> Parsed {
+        loop {
+            match self.kind(0) {
+                None | Some(TokenKind::Punct('}')) => return Ok(()),
+                Some(TokenKind::Ident) if ends.contains(&self.word().as_str()) => return Ok(()),
+                _ => self.statement()?,
+            }
+        }
+    }
+
+    fn block(&mut self) -> Parsed {
+        let open = self.expect("{")?;
+        self.statements(&[])?;
+        self.close("}", open)
+    }
+
+    /// A statement, or the statements of the alternative syntax up to a
+    /// keyword, e.g. `endwhile`.
+    fn body(&mut self, end: &str) -> Parsed {
+        if !self.eat_op(":") {
+            return self.statement();
+        }
+
+        self.statements(&[end])?;
+        self.expect_word(end)?;
+        self.end()
+    }
+
+    fn parenthesized(&mut self) -> Parsed {
+        let open = self.expect("(")?;
+        self.expression()?;
+        self.close(")", open)
+    }
+
+    fn statement(&mut self) -> Parsed {
+        let Some(token) = self.peek() else {
+            return Err(self.unexpected("a statement"));
+        };
+
+        match token.kind {
+            TokenKind::InlineHtml | TokenKind::CloseTag | TokenKind::Punct(';') => {
+                self.pos += 1;
+                return Ok(());
+            }
+            TokenKind::OpenTag => {
+                self.pos += 1;
+
+                // `<?=` echoes the expressions up to the end of the statement
+                if self.code[token.start..token.end].ends_with('=') {
+                    self.expressions()?;
+                    self.end()?;
+                }
+
+                return Ok(());
+            }
+            TokenKind::Punct('{') => return self.block(),
+            TokenKind::Punct('#') if self.op_at(1) == "[" => {
+                self.attributes()?;
+
+                return match self.word().as_str() {
+                    "function" if self.kind(1) != Some(TokenKind::Punct('(')) => self.function(),
+                    "abstract" | "final" | "readonly" | "class" | "interface" | "trait"
+                    | "enum" => self.class_like(),
+                    _ => self.expression_statement(),
+                };
+            }
+            TokenKind::Punct('@') if self.kind(1) == Some(TokenKind::Ident) => {
+                let annotated = self.kind(2) == Some(TokenKind::Ident)
+                    || self.kind(2) == Some(TokenKind::Punct('('))
+                        && self.text(1).starts_with(char::is_uppercase);
+
+                if annotated {
+                    return Err(self.error(format!("Java annotation `@{}`", self.text(1))));
+                }
+            }
+            _ => {}
+        }
+
+        if token.kind != TokenKind::Ident {
+            return self.expression_statement();
+        }
+
+        let word = self.word();
+        let next = self.op_at(1);
+
+        match word.as_str() {
+            "if" => {
+                self.pos += 1;
+                self.parenthesized()?;
+
+                if self.eat_op(":") {
+                    loop {
+                        self.statements(&["elseif", "else", "endif"])?;
+
+                        match self.word().as_str() {
+                            "elseif" => {
+                                self.pos += 1;
+                                self.parenthesized()?;
+                                self.expect(":")?;
+                            }
+                            "else" => {
+                                self.pos += 1;
+                                self.expect(":")?;
+                            }
+                            _ => break,
+                        }
+                    }
+
+                    self.expect_word("endif")?;
+                    return self.end();
+                }
+
+                self.statement()?;
+
+                while self.eat_word("elseif") {
+                    self.parenthesized()?;
+                    self.statement()?;
+                }
+
+                if self.eat_word("else") {
+                    self.statement()?;
+                }
+
+                Ok(())
+            }
+            "while" => {
+                self.pos += 1;
+                self.parenthesized()?;
+                self.body("endwhile")
+            }
+            "do" => {
+                self.pos += 1;
+                self.statement()?;
+                self.expect_word("while")?;
+                self.parenthesized()?;
+                self.end()
+            }
+            "for" => {
+                self.pos += 1;
+                let open = self.expect("(")?;
+
+                for end in [";", ";"] {
+                    if self.op() != end {
+                        self.expressions()?;
+                    }
+
+                    self.expect(end)?;
+                }
+
+                if self.op() != ")" {
+                    self.expressions()?;
+                }
+
+                self.close(")", open)?;
+                self.body("endfor")
+            }
+            "foreach" => {
+                self.pos += 1;
+                let open = self.expect("(")?;
+                self.expression()?;
+                self.expect_word("as")?;
+                self.eat_op("&");
+                self.expression()?;
+
+                if self.eat_op("=>") {
+                    self.eat_op("&");
+                    self.expression()?;
+                }
+
+                self.close(")", open)?;
+                self.body("endforeach")
+            }
+            "switch" => {
+                self.pos += 1;
+                self.parenthesized()?;
+                self.switch()
+            }
+            "break" | "continue" | "return" => {
+                self.pos += 1;
+
+                if !self.is_end() {
+                    self.expression()?;
+                }
+
+                self.end()
+            }
+            "echo" => {
+                self.pos += 1;
+                self.expressions()?;
+                self.end()
+            }
+            "global" => {
+                self.pos += 1;
+
+                loop {
+                    self.unary()?;
+
+                    if !self.eat_op(",") {
+                        break;
+                    }
+                }
+
+                self.end()
+            }
+            "static" if self.kind(1) == Some(TokenKind::Variable) => {
+                self.pos += 1;
+
+                loop {
+                    self.variable()?;
+
+                    if self.eat_op("=") {
+                        self.expression()?;
+                    }
+
+                    if !self.eat_op(",") {
+                        break;
+                    }
+                }
+
+                self.end()
+            }
+            "unset" if next == "(" => {
+                self.pos += 1;
+                self.arguments()?;
+                self.end()
+            }
+            "try" => {
+                self.pos += 1;
+                self.block()?;
+                let mut handled = false;
+
+                while self.eat_word("catch") {
+                    let open = self.expect("(")?;
+                    self.name()?;
+
+                    while self.eat_op("|") {
+                        self.name()?;
+                    }
+
+                    if self.kind(0) == Some(TokenKind::Variable) {
+                        self.pos += 1;
+                    }
+
+                    self.close(")", open)?;
+                    self.block()?;
+                    handled = true;
+                }
+
+                if self.eat_word("finally") {
+                    self.block()?;
+                    handled = true;
+                }
+
+                match handled {
+                    true => Ok(()),
+                    false => Err(self.unexpected("`catch` or `finally`")),
+                }
+            }
+            "goto" => {
+                self.pos += 1;
+                self.identifier()?;
+                self.end()
+            }
+            "declare" => {
+                self.pos += 1;
+                let open = self.expect("(")?;
+
+                loop {
+                    self.identifier()?;
+                    self.expect("=")?;
+                    self.expression()?;
+
+                    if !self.eat_op(",") {
+                        break;
+                    }
+                }
+
+                self.close(")", open)?;
+
+                match self.is_end() && self.peek().is_some() {
+                    true => self.end(),
+                    false => self.body("enddeclare"),
+                }
+            }
+            "namespace" if next != "\\" => {
+                self.pos += 1;
+
+                if self.kind(0) == Some(TokenKind::Ident) {
+                    self.name()?;
+                }
+
+                match self.op() {
+                    "{" => self.block(),
+                    _ => self.end(),
+                }
+            }
+            "use" => {
+                self.pos += 1;
+                self.imports()?;
+                self.end()
+            }
+            "const" => {
+                self.pos += 1;
+                self.constants()
+            }
+            "function" if next != "(" && !(next == "&" && self.op_at(2) == "(") => self.function(),
+            "abstract" | "final" | "class" | "interface" | "trait" => self.class_like(),
+            "readonly" if next != "(" => self.class_like(),
+            "enum"
+                if self.kind(1) == Some(TokenKind::Ident)
+                    && (matches!(self.op_at(2), "{" | ":") || self.is_word_at(2, "implements")) =>
+            {
+                self.class_like()
+            }
+            "__halt_compiler" => {
+                self.pos = self.tokens.len();
+                Ok(())
+            }
+            _ if next == ":" => {
+                // Label
+                self.pos += 2;
+                Ok(())
+            }
+            _ if self.kind(1) == Some(TokenKind::Ident)
+                && !KEYWORDS.contains(&word.as_str())
+                && !KEYWORDS.contains(&self.text(1).to_ascii_lowercase().as_str()) =>
+            {
+                Err(self.error(format!(
+                    "Java-style declaration `{} {}`, variables need a `$`",
+                    self.text(0),
+                    self.text(1)
+                )))
+            }
+            _ if next == "<" && self.generic_declaration() => Err(self.error(format!(
+                "Java generics `{}<`, PHP types take no type parameters",
+                self.text(0)
+            ))),
+            _ => self.expression_statement(),
+        }
+    }
+
+    /// Whether a generic type followed by a variable comes next, e.g.
+    /// `List<String> $names`.
+    fn generic_declaration(&self) -> bool {
+        let mut depth = 0;
+
+        for n in 1.. {
+            match (self.kind(n), self.text(n)) {
+                (Some(TokenKind::Punct(_)), "<") => depth += 1,
+                (Some(TokenKind::Punct(_)), ">") => {
+                    depth -= 1;
+
+                    if depth == 0 {
+                        return self.kind(n + 1) == Some(TokenKind::Variable);
+                    }
+                }
+                (Some(TokenKind::Ident | TokenKind::Punct(',' | '\\' | '?')), _) => {}
+                _ => return false,
+            }
+        }
+
+        false
+    }
+
+    fn expression_statement(&mut self) -> Parsed {
+        self.expression()?;
+        self.end()
+    }
+
+    fn expressions(&mut self) -> Parsed {
+        loop {
+            self.expression()?;
+
+            if !self.eat_op(",") {
+                return Ok(());
+            }
+        }
+    }
+
+    fn switch(&mut self) -> Parsed {
+        let alternative = self.eat_op(":");
+
+        let open = match alternative {
+            true => 0,
+            false => self.expect("{")?,
+        };
+
+        loop {
+            match self.word().as_str() {
+                "case" => {
+                    self.pos += 1;
+                    self.expression()?;
+                }
+                "default" => self.pos += 1,
+                "endswitch" if alternative => {
+                    self.pos += 1;
+                    return self.end();
+                }
+                _ if !alternative && self.op() == "}" => return self.close("}", open),
+                _ => return Err(self.unexpected("`case` or `default`")),
+            }
+
+            if !self.eat_op(";") {
+                self.expect(":")?;
+            }
+
+            self.statements(&["case", "default", "endswitch"])?;
+        }
+    }
+
+    /// The imports of a `use` statement, possibly grouped.
+    fn imports(&mut self) -> Parsed {
+        if self.is_word("function") || self.is_word("const") {
+            self.pos += 1;
+        }
+
+        loop {
+            self.eat_op("\\");
+            self.identifier()?;
+
+            while self.eat_op("\\") {
+                if self.op() == "{" {
+                    let open = self.expect("{")?;
+
+                    while self.op() != "}" {
+                        if self.is_word("function") || self.is_word("const") {
+                            self.pos += 1;
+                        }
+
+                        self.name()?;
+
+                        if self.eat_word("as") {
+                            self.identifier()?;
+                        }
+
+                        if !self.eat_op(",") {
+                            break;
+                        }
+                    }
+
+                    self.close("}", open)?;
+                    break;
+                }
+
+                self.identifier()?;
+            }
+
+            if self.eat_word("as") {
+                self.identifier()?;
+            }
+
+            if !self.eat_op(",") {
+                return Ok(());
+            }
+        }
+    }
+
+    /// `A = 1, B = 2;` after `const`.
+    fn constants(&mut self) -> Parsed {
+        loop {
+            self.identifier()?;
+            self.expect("=")?;
+            self.expression()?;
+
+            if !self.eat_op(",") {
+                return self.end();
+            }
+        }
+    }
+
+    /// A possibly qualified name, e.g. `\Foo\Bar` or `namespace\Foo`.
+    fn name(&mut self) -> Parsed {
+        self.eat_op("\\");
+        self.identifier()?;
+
+        while self.op() == "\\" && self.kind(1) == Some(TokenKind::Ident) {
+            self.pos += 2;
+        }
+
+        Ok(())
+    }
+
+    fn names(&mut self) -> Parsed {
+        loop {
+            self.name()?;
+
+            if !self.eat_op(",") {
+                return Ok(());
+            }
+        }
+    }
+
+    /// A type, with `?`, `|`, `&` and parentheses.
+    fn ty(&mut self) -> Parsed {
+        if self.eat_op("?") {
+            self.name()?;
+        } else {
+            loop {
+                if self.op() == "(" {
+                    let open = self.expect("(")?;
+                    self.name()?;
+
+                    while self.eat_op("&") {
+                        self.name()?;
+                    }
+
+                    self.close(")", open)?;
+                } else {
+                    self.name()?;
+                }
+
+                // Unlike a parameter taken by reference
+                let intersection = self.op() == "&"
+                    && !matches!(self.kind(1), Some(TokenKind::Variable))
+                    && !matches!(self.op_at(1), "&" | "...");
+
+                if !(self.eat_op("|") || intersection && self.eat_op("&")) {
+                    break;
+                }
+            }
+        }
+
+        match self.op() {
+            "<" => Err(self.error(format!(
+                "Java generics `{}<`, PHP types take no type parameters",
+                self.text_before()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Attribute groups, e.g. `#[Foo, Bar(1)]`.
+    fn attributes(&mut self) -> Parsed {
+        while self.op() == "#" && self.op_at(1) == "[" {
+            let open = self.expect("#")?;
+            self.pos += 1;
+
+            while self.op() != "]" {
+                self.name()?;
+
+                if self.op() == "(" {
+                    self.arguments()?;
+                }
+
+                if !self.eat_op(",") {
+                    break;
+                }
+            }
+
+            self.close("]", open + 1)?;
+        }
+
+        Ok(())
+    }
+
+    fn parameters(&mut self) -> Parsed {
+        let open = self.expect("(")?;
+
+        while self.op() != ")" {
+            self.attributes()?;
+
+            while ["public", "protected", "private", "readonly"].contains(&self.word().as_str()) {
+                self.pos += 1;
+            }
+
+            if self.kind(0) != Some(TokenKind::Variable) && !matches!(self.op(), "&" | "...") {
+                self.ty()?;
+            }
+
+            self.eat_op("&");
+            self.eat_op("...");
+            self.variable()?;
+
+            if self.eat_op("=") {
+                self.expression()?;
+            }
+
+            if !self.eat_op(",") {
+                break;
+            }
+        }
+
+        self.close(")", open)
+    }
+
+    /// `: type` after the parameters of a function.
+    fn return_type(&mut self) -> Parsed {
+        match self.eat_op(":") {
+            true => self.ty(),
+            false => Ok(()),
+        }
+    }
+
+    fn function(&mut self) -> Parsed {
+        self.expect_word("function")?;
+        self.eat_op("&");
+        self.identifier()?;
+        self.parameters()?;
+        self.return_type()?;
+        self.block()
+    }
+
+    fn class_like(&mut self) -> Parsed {
+        while ["abstract", "final", "readonly"].contains(&self.word().as_str()) {
+            self.pos += 1;
+        }
+
+        match self.word().as_str() {
+            "class" => {
+                self.pos += 1;
+                self.identifier()?;
+                self.class_rest()
+            }
+            "interface" => {
+                self.pos += 1;
+                self.identifier()?;
+
+                if self.eat_word("extends") {
+                    self.names()?;
+                }
+
+                self.class_body()
+            }
+            "trait" => {
+                self.pos += 1;
+                self.identifier()?;
+                self.class_body()
+            }
+            "enum" => {
+                self.pos += 1;
+                self.identifier()?;
+
+                if self.eat_op(":") {
+                    self.ty()?;
+                }
+
+                if self.eat_word("implements") {
+                    self.names()?;
+                }
+
+                self.class_body()
+            }
+            _ => Err(self.unexpected("`class`")),
+        }
+    }
+
+    /// What follows the name of a class, or the arguments of an anonymous one.
+    fn class_rest(&mut self) -> Parsed {
+        if self.eat_word("extends") {
+            self.name()?;
+        }
+
+        if self.eat_word("implements") {
+            self.names()?;
+        }
+
+        self.class_body()
+    }
+
+    fn class_body(&mut self) -> Parsed {
+        let open = self.expect("{")?;
+
+        while self.peek().is_some() && self.op() != "}" {
+            self.member()?;
+        }
+
+        self.close("}", open)
+    }
+
+    fn member(&mut self) -> Parsed {
+        self.attributes()?;
+
+        match self.word().as_str() {
+            "use" => {
+                self.pos += 1;
+                self.names()?;
+
+                if self.op() != "{" {
+                    return self.end();
+                }
+
+                let open = self.expect("{")?;
+
+                while self.peek().is_some() && self.op() != "}" {
+                    self.name()?;
+
+                    if self.eat_op("::") {
+                        self.identifier()?;
+                    }
+
+                    if self.eat_word("insteadof") {
+                        self.names()?;
+                    } else {
+                        self.expect_word("as")?;
+
+                        if MEMBER_MODIFIERS.contains(&self.word().as_str()) {
+                            self.pos += 1;
+                        }
+
+                        if self.kind(0) == Some(TokenKind::Ident) {
+                            self.pos += 1;
+                        }
+                    }
+
+                    self.end()?;
+                }
+
+                return self.close("}", open);
+            }
+            "case" => {
+                self.pos += 1;
+                self.identifier()?;
+
+                if self.eat_op("=") {
+                    self.expression()?;
+                }
+
+                return self.end();
+            }
+            _ => {}
+        }
+
+        let mut modifiers = 0;
+
+        while MEMBER_MODIFIERS.contains(&self.word().as_str()) {
+            self.pos += 1;
+            modifiers += 1;
+        }
+
+        match self.word().as_str() {
+            "const" => {
+                self.pos += 1;
+
+                if self.op_at(1) != "=" {
+                    self.ty()?;
+                }
+
+                self.constants()
+            }
+            "function" => {
+                self.pos += 1;
+                self.eat_op("&");
+                self.identifier()?;
+                self.parameters()?;
+                self.return_type()?;
+
+                match self.op() {
+                    "{" => self.block(),
+                    _ => self.end(),
+                }
+            }
+            _ if modifiers > 0 => {
+                if self.kind(0) != Some(TokenKind::Variable) {
+                    self.ty()?;
+                }
+
+                loop {
+                    self.variable()?;
+
+                    if self.eat_op("=") {
+                        self.expression()?;
+                    }
+
+                    if !self.eat_op(",") {
+                        return self.end();
+                    }
+                }
+            }
+            _ if self.op() == "@" && self.kind(1) == Some(TokenKind::Ident) => {
+                Err(self.error(format!("Java annotation `@{}`", self.text(1))))
+            }
+            _ => Err(self.unexpected("a property, method or constant declaration")),
+        }
+    }
+
+    fn expression(&mut self) -> Parsed<Expr> {
+        self.binary(0)
+    }
+
+    /// Operators and operands binding at least as tightly as `min`.
+    fn binary(&mut self, min: u8) -> Parsed<Expr> {
+        let mut left = self.unary()?;
+        let mut non_associative = None;
+        let mut short_ternary = None;
+
+        loop {
+            let op = match self.kind(0) {
+                Some(TokenKind::Ident) => self.word(),
+                _ => self.op().to_owned(),
+            };
+
+            // `!$a = f()` and `$a && $b = 1` assign before applying the operator
+            if ASSIGNMENTS.contains(&op.as_str()) {
+                if left != Expr::Variable && !(op == "=" && left == Expr::Array) {
+                    return Err(self.error(format!("Unexpected `{}`, cannot assign to it", op)));
+                }
+
+                self.pos += op.len();
+
+                if op == "=" {
+                    self.eat_op("&");
+                }
+
+                self.binary(ASSIGNMENT)?;
+                left = Expr::Other;
+                continue;
+            }
+
+            if op == "?" {
+                if min > TERNARY {
+                    break;
+                }
+
+                let short = self.op_at(1) == ":";
+
+                // PHP 8 only chains short ternaries, e.g. `$a ?: $b ?: $c`
+                if short_ternary.is_some_and(|previous| !(previous && short)) {
+                    return Err(self.error("Nested ternary operators need parentheses".to_owned()));
+                }
+
+                self.pos += 1;
+
+                if !short {
+                    self.binary(ASSIGNMENT)?;
+                }
+
+                self.expect(":")?;
+                self.binary(TERNARY + 1)?;
+                short_ternary = Some(short);
+                left = Expr::Other;
+                continue;
+            }
+
+            let Some((precedence, associativity)) = binary(&op) else {
+                break;
+            };
+
+            if precedence < min {
+                break;
+            }
+
+            if non_associative == Some(precedence) {
+                let generics = op == ">" && non_associative == Some(13);
+
+                return Err(self.error(format!(
+                    "Unexpected `{}`, comparisons cannot be chained{}",
+                    op,
+                    match generics {
+                        true => " and PHP has no generics",
+                        false => "",
+                    }
+                )));
+            }
+
+            self.pos += match self.kind(0) {
+                Some(TokenKind::Ident) => 1,
+                _ => op.len(),
+            };
+
+            self.binary(match associativity {
+                Associativity::Right => precedence,
+                _ => precedence + 1,
+            })?;
+
+            non_associative = (associativity == Associativity::None).then_some(precedence);
+            left = Expr::Other;
+        }
+
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Parsed<Expr> {
+        match self.op() {
+            "!" => {
+                self.pos += 1;
+                self.binary(NOT)?;
+                return Ok(Expr::Other);
+            }
+            op @ ("-" | "+" | "~" | "@" | "++" | "--") => {
+                self.pos += op.len();
+                self.binary(UNARY)?;
+                return Ok(Expr::Other);
+            }
+            "(" if self.kind(1) == Some(TokenKind::Ident)
+                && self.op_at(2) == ")"
+                && CASTS.contains(&self.text(1).to_ascii_lowercase().as_str()) =>
+            {
+                // `(String) $o` is valid, but left over from Java
+                if self.text(1).starts_with(char::is_uppercase) {
+                    return Err(self.error(format!(
+                        "Java-style cast `({})`, PHP casts are lowercase, e.g. `({})`",
+                        self.text(1),
+                        self.text(1).to_ascii_lowercase()
+                    )));
+                }
+
+                self.pos += 3;
+                self.binary(UNARY)?;
+                return Ok(Expr::Other);
+            }
+            _ => {}
+        }
+
+        match self.word().as_str() {
+            "new" => {
+                self.instantiation()?;
+                return Ok(Expr::Other);
+            }
+            "clone" => {
+                self.pos += 1;
+                self.binary(UNARY)?;
+                return Ok(Expr::Other);
+            }
+            "print" | "throw" | "include" | "include_once" | "require" | "require_once" => {
+                self.pos += 1;
+                self.binary(ASSIGNMENT)?;
+                return Ok(Expr::Other);
+            }
+            "yield" => {
+                self.pos += 1;
+
+                if self.eat_word("from") {
+                    self.binary(ASSIGNMENT)?;
+                } else if !self.is_end() && !matches!(self.op(), ")" | "," | "]") {
+                    self.binary(ASSIGNMENT)?;
+
+                    if self.eat_op("=>") {
+                        self.binary(ASSIGNMENT)?;
+                    }
+                }
+
+                return Ok(Expr::Other);
+            }
+            _ => {}
+        }
+
+        self.postfix()
+    }
+
+    /// An operand with its property accesses, calls and array accesses.
+    fn postfix(&mut self) -> Parsed<Expr> {
+        let Some(mut expr) = self.primary()? else {
+            return Ok(Expr::Other);
+        };
+
+        loop {
+            match self.op() {
+                "[" => {
+                    let open = self.expect("[")?;
+
+                    if self.op() != "]" {
+                        self.expression()?;
+                    }
+
+                    self.close("]", open)?;
+                    expr = Expr::Variable;
+                }
+                op @ ("->" | "?->") => {
+                    self.pos += op.len();
+                    self.member_name()?;
+                    expr = Expr::Variable;
+                }
+                "::" => {
+                    self.pos += 2;
+
+                    expr = match self.kind(0) {
+                        Some(TokenKind::Variable) | Some(TokenKind::Punct('$')) => {
+                            self.simple_variable()?;
+                            Expr::Variable
+                        }
+                        _ => {
+                            self.member_name()?;
+                            Expr::Other
+                        }
+                    };
+                }
+                "(" => {
+                    self.arguments()?;
+                    expr = Expr::Other;
+                }
+                op @ ("++" | "--") => {
+                    self.pos += op.len();
+                    return Ok(Expr::Other);
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    /// The name of a property, method or constant, which can be a keyword.
+    fn member_name(&mut self) -> Parsed {
+        match self.kind(0) {
+            Some(TokenKind::Ident) => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(TokenKind::Punct('{')) => {
+                let open = self.expect("{")?;
+                self.expression()?;
+                self.close("}", open)
+            }
+            Some(TokenKind::Variable | TokenKind::Punct('$')) => self.simple_variable(),
+            _ => Err(self.unexpected("a member name")),
+        }
+    }
+
+    /// `$a`, `$$a` or `${expression}`.
+    fn simple_variable(&mut self) -> Parsed {
+        if self.kind(0) == Some(TokenKind::Variable) {
+            self.pos += 1;
+            return Ok(());
+        }
+
+        self.expect("$")?;
+
+        match self.op() {
+            "{" => {
+                let open = self.expect("{")?;
+                self.expression()?;
+                self.close("}", open)
+            }
+            _ => self.simple_variable(),
+        }
+    }
+
+    fn arguments(&mut self) -> Parsed {
+        let open = self.expect("(")?;
+
+        while self.op() != ")" {
+            if self.eat_op("...") {
+                // First-class callable syntax
+                if self.op() == ")" {
+                    break;
+                }
+            } else if self.kind(0) == Some(TokenKind::Ident) && self.op_at(1) == ":" {
+                // Named argument
+                self.pos += 2;
+            }
+
+            self.expression()?;
+
+            if !self.eat_op(",") {
+                break;
+            }
+        }
+
+        self.close(")", open)
+    }
+
+    /// Elements of an array or of `list()`, some of which can be left out
+    /// when destructuring.
+    fn elements(&mut self, close: &str) -> Parsed {
+        let open = self.expect(if close == "]" { "[" } else { "(" })?;
+
+        while self.op() != close {
+            if self.op() == "," {
+                self.pos += 1;
+                continue;
+            }
+
+            if !self.eat_op("...") {
+                self.eat_op("&");
+            }
+
+            self.expression()?;
+
+            if self.eat_op("=>") {
+                self.eat_op("&");
+                self.expression()?;
+            }
+
+            if !self.eat_op(",") {
+                break;
+            }
+        }
+
+        self.close(close, open)
+    }
+
+    fn instantiation(&mut self) -> Parsed {
+        self.expect_word("new")?;
+        self.attributes()?;
+
+        if self.is_word("class") || self.is_word("readonly") && self.is_word_at(1, "class") {
+            self.pos += if self.is_word("readonly") { 2 } else { 1 };
+
+            if self.op() == "(" {
+                self.arguments()?;
+            }
+
+            return self.class_rest();
+        }
+
+        match self.kind(0) {
+            Some(TokenKind::Punct('(')) => self.parenthesized()?,
+            Some(TokenKind::Variable | TokenKind::Punct('$')) => {
+                self.simple_variable()?;
+
+                // Properties, static properties and array elements, not calls
+                loop {
+                    match self.op() {
+                        "[" => {
+                            let open = self.expect("[")?;
+                            self.expression()?;
+                            self.close("]", open)?;
+                        }
+                        op @ ("->" | "?->") => {
+                            self.pos += op.len();
+                            self.member_name()?;
+                        }
+                        "::" => {
+                            self.pos += 2;
+                            self.simple_variable()?;
+                        }
+                        _ => break,
+                    }
+                }
+            }
+            _ => self.name()?,
+        }
+
+        if self.op() == "(" {
+            self.arguments()?;
+        }
+
+        Ok(())
+    }
+
+    /// A closure or arrow function, after its attributes.
+    fn closure(&mut self) -> Parsed {
+        self.eat_word("static");
+
+        if self.eat_word("fn") {
+            self.eat_op("&");
+            self.parameters()?;
+            self.return_type()?;
+            self.expect("=>")?;
+            self.binary(ASSIGNMENT)?;
+            return Ok(());
+        }
+
+        self.expect_word("function")?;
+        self.eat_op("&");
+        self.parameters()?;
+
+        if self.eat_word("use") {
+            let open = self.expect("(")?;
+
+            while self.op() != ")" {
+                self.eat_op("&");
+                self.variable()?;
+
+                if !self.eat_op(",") {
+                    break;
+                }
+            }
+
+            self.close(")", open)?;
+        }
+
+        self.return_type()?;
+        self.block()
+    }
+
+    fn match_arms(&mut self) -> Parsed {
+        let open = self.expect("{")?;
+
+        while self.peek().is_some() && self.op() != "}" {
+            if self.is_word("default") && self.op_at(1) != "::" {
+                self.pos += 1;
+                self.eat_op(",");
+            } else {
+                while self.op() != "=>" {
+                    self.expression()?;
+
+                    if !self.eat_op(",") {
+                        break;
+                    }
+                }
+            }
+
+            self.expect("=>")?;
+            self.expression()?;
+
+            if !self.eat_op(",") {
+                break;
+            }
+        }
+
+        self.close("}", open)
+    }
+
+    /// An operand, unless it cannot be followed by property accesses, calls
+    /// or array accesses.
+    fn primary(&mut self) -> Parsed<Option<Expr>> {
+        let Some(token) = self.peek() else {
+            return Err(self.unexpected("an expression"));
+        };
+
+        match token.kind {
+            TokenKind::Variable => {
+                self.pos += 1;
+                return Ok(Some(Expr::Variable));
+            }
+            TokenKind::Literal => {
+                self.pos += 1;
+                return Ok(Some(Expr::Other));
+            }
+            TokenKind::Punct('$') => {
+                self.simple_variable()?;
+                return Ok(Some(Expr::Variable));
+            }
+            TokenKind::Punct('[') => {
+                self.elements("]")?;
+                return Ok(Some(Expr::Array));
+            }
+            TokenKind::Punct('(') => {
+                let open = self.expect("(")?;
+                let cast = self.kind(0) == Some(TokenKind::Ident) && self.op_at(1) == ")";
+                self.expression()?;
+                self.close(")", open)?;
+
+                if cast && self.kind(0) == Some(TokenKind::Variable) {
+                    return Err(SyntaxError::new(
+                        self.code,
+                        open,
+                        format!(
+                            "Java-style cast `{}`, PHP only casts to scalar types, arrays \
+                            and objects",
+                            &self.code[open..self.tokens[self.pos - 1].end]
+                        ),
+                    ));
+                }
+
+                return Ok(Some(Expr::Other));
+            }
+            TokenKind::Punct('#') if self.op_at(1) == "[" => {
+                self.attributes()?;
+                self.closure()?;
+                return Ok(None);
+            }
+            TokenKind::Punct('\\') => {
+                self.name()?;
+                return Ok(Some(Expr::Other));
+            }
+            TokenKind::Ident => {}
+            _ => return Err(self.unexpected("an expression")),
+        }
+
+        let word = self.word();
+        let next = self.op_at(1);
+
+        match word.as_str() {
+            "array" | "list" if next == "(" => {
+                self.pos += 1;
+                self.elements(")")?;
+                Ok(Some(Expr::Array))
+            }
+            "function" | "fn" => {
+                self.closure()?;
+                Ok(None)
+            }
+            "static" if self.is_word_at(1, "function") || self.is_word_at(1, "fn") => {
+                self.closure()?;
+                Ok(None)
+            }
+            "match" if next == "(" => {
+                self.pos += 1;
+                self.parenthesized()?;
+                self.match_arms()?;
+                Ok(Some(Expr::Other))
+            }
+            _ if STATEMENT_KEYWORDS.contains(&word.as_str()) && next != "\\" => {
+                Err(self.unexpected("an expression"))
+            }
+            _ => {
+                self.name()?;
+                Ok(Some(Expr::Other))
+            }
+        }
+    }
+}
+
+/// Offsets and names of the features of [`FEATURES`] some code uses.
+fn features(code: &str, tokens: &[Token]) -> Vec<(usize, &'static str)> {
+    let text = |i: usize| {
+        tokens
+            .get(i)
+            .map_or("", |token| &code[token.start..token.end])
+    };
+    let kind = |i: usize| tokens.get(i).map(|token| token.kind);
+    let adjacent = |i: usize| {
+        tokens
+            .get(i + 1)
+            .is_some_and(|next| next.start == tokens[i].end)
+    };
+    let is_type = |j: usize| {
+        matches!(
+            kind(j),
+            Some(TokenKind::Ident | TokenKind::Punct('|' | '&' | '\\' | '?' | '(' | ')'))
+        )
+    };
+
+    // The tokens of the type around `i`, with the parentheses of its groups but
+    // not those of the parameters, e.g. `(A&B)|null` in `f((A&B)|null $a)`
+    let type_around = |i: usize| {
+        let mut depth = 0;
+        let mut groups = 0;
+        let mut start = i;
+
+        while start > 0 && is_type(start - 1) {
+            match kind(start - 1) {
+                Some(TokenKind::Punct(')')) => depth += 1,
+                Some(TokenKind::Punct('(')) if depth > 0 => depth -= 1,
+                Some(TokenKind::Punct('(')) => {
+                    // Parameters follow the name of a function, types modifiers
+                    let parameters = start >= 2
+                        && kind(start - 2) == Some(TokenKind::Ident)
+                        && !MEMBER_MODIFIERS
+                            .contains(&text(start - 2).to_ascii_lowercase().as_str());
+
+                    match parameters {
+                        true => break,
+                        false => groups += 1,
+                    }
+                }
+                _ => {}
+            }
+
+            start -= 1;
+        }
+
+        let mut depth = 0;
+        let mut end = i;
+
+        while is_type(end) {
+            match kind(end) {
+                Some(TokenKind::Punct('(')) => depth += 1,
+                Some(TokenKind::Punct(')')) if depth > 0 => depth -= 1,
+                Some(TokenKind::Punct(')')) if groups > 0 => groups -= 1,
+                Some(TokenKind::Punct(')')) => break,
+                // Taken by reference
+                Some(TokenKind::Punct('&')) if kind(end + 1) == Some(TokenKind::Variable) => break,
+                _ => {}
+            }
+
+            end += 1;
+        }
+
+        start..end
+    };
+
+    // Types of parameters, properties and return values, unlike operators
+    let is_declared = |range: &Range<usize>| {
+        let before_variable = matches!(kind(range.end), Some(TokenKind::Variable))
+            || text(range.end) == "&" && kind(range.end + 1) == Some(TokenKind::Variable)
+            || text(range.end) == ".";
+
+        let returned =
+            range.start >= 2 && text(range.start - 1) == ":" && text(range.start - 2) == ")";
+
+        !range.is_empty()
+            && matches!(
+                kind(range.end - 1),
+                Some(TokenKind::Ident | TokenKind::Punct(')'))
+            )
+            && (before_variable || returned)
+    };
+
+    // Whether the `:` at `i` is part of a ternary operator, unlike that of `case`
+    let is_ternary = |i: usize| {
+        (0..i)
+            .rev()
+            .take_while(|&j| !matches!(text(j), ";" | "{" | "}"))
+            .any(|j| {
+                text(j) == "?"
+                    && !matches!(text(j + 1), "-" | "?" | ">")
+                    && (j == 0 || text(j - 1) != "?")
+            })
+    };
+
+    let mut features = Vec::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let previous = |n: usize| i.checked_sub(n).map_or("", text);
+        let is_member =
+            matches!(previous(1), ">" | ":") || previous(1).eq_ignore_ascii_case("function");
+        let is_return_type = previous(1) == ":" && previous(2) == ")";
+
+        let feature = match token.kind {
+            TokenKind::Ident => match text(i).to_ascii_lowercase().as_str() {
+                "enum"
+                    if !is_member
+                        && kind(i + 1) == Some(TokenKind::Ident)
+                        && matches!(text(i + 2), "{" | ":" | "implements") =>
+                {
+                    Some("Enum")
+                }
+                // Unlike names, e.g. `class ReadOnly` and `const READONLY`
+                "readonly"
+                    if !is_member
+                        && !matches!(
+                            previous(1).to_ascii_lowercase().as_str(),
+                            "class" | "const" | "\\" | "new" | "extends" | "implements"
+                        ) =>
+                {
+                    match [text(i + 1), text(i + 2)].map(|t| t.to_ascii_lowercase()) {
+                        [class, _] if class == "class" => Some("Readonly class"),
+                        [modifier, class]
+                            if ["final", "abstract"].contains(&modifier.as_str())
+                                && class == "class" =>
+                        {
+                            Some("Readonly class")
+                        }
+                        _ => Some("Readonly property"),
+                    }
+                }
+                "match" if !is_member && text(i + 1) == "(" => Some("`match` expression"),
+                "mixed" if is_return_type || kind(i + 1) == Some(TokenKind::Variable) => {
+                    Some("`mixed` type")
+                }
+                "static" if is_return_type => Some("`static` return type"),
+                "catch" if text(i + 1) == "(" => {
+                    let caught = (i + 2..tokens.len())
+                        .take_while(|&j| text(j) != ")")
+                        .any(|j| kind(j) == Some(TokenKind::Variable));
+
+                    (!caught).then_some("Catch without a variable")
+                }
+                "new" if text(i + 1) == "(" => Some("`new` with an arbitrary expression"),
+                // Unlike statements, e.g. `$a ?? throw new E()` and `fn() => throw $e`
+                "throw"
+                    if matches!(previous(1), "?" | "=" | ">" | "(" | "," | "&" | "|")
+                        || previous(1) == ":" && is_ternary(i - 1)
+                        || ["and", "or", "xor"]
+                            .contains(&previous(1).to_ascii_lowercase().as_str()) =>
+                {
+                    Some("`throw` expression")
+                }
+                "never" if is_return_type => Some("`never` return type"),
+                "__construct" if previous(1).eq_ignore_ascii_case("function") => {
+                    let mut depth = 0;
+                    let mut promoted = false;
+
+                    for (j, token) in tokens.iter().enumerate().skip(i + 1) {
+                        match token.kind {
+                            TokenKind::Punct('(') => depth += 1,
+                            TokenKind::Punct(')') if depth == 1 => break,
+                            TokenKind::Punct(')') => depth -= 1,
+                            TokenKind::Ident if depth == 1 => {
+                                promoted |= ["public", "protected", "private", "readonly"]
+                                    .contains(&text(j).to_ascii_lowercase().as_str());
+                            }
+                            _ => {}
+                        }
+                    }
+
+                    promoted.then_some("Constructor property promotion")
+                }
+                "const"
+                    if kind(i + 1) == Some(TokenKind::Ident)
+                        && kind(i + 2) == Some(TokenKind::Ident)
+                        && text(i + 3) == "=" =>
+                {
+                    Some("Typed class constant")
+                }
+                _ => {
+                    // `f(name: $value)`, unlike `a ? b : c`, `case B:` and `A::b`
+                    let named = matches!(previous(1), "(" | ",")
+                        && text(i + 1) == ":"
+                        && text(i + 2) != ":";
+
+                    named.then_some("Named argument")
+                }
+            },
+            TokenKind::Literal if text(i).len() > 2 && text(i)[..2].eq_ignore_ascii_case("0o") => {
+                Some("Explicit octal literal `0o`")
+            }
+            TokenKind::Punct('?') if adjacent(i) && text(i + 1) == "-" && text(i + 2) == ">" => {
+                Some("Nullsafe operator `?->`")
+            }
+            // Unless the rest of the line is commented out along with it
+            TokenKind::Punct('#') if adjacent(i) && text(i + 1) == "[" => {
+                let mut depth = 0;
+
+                let end = (i + 1..tokens.len()).find(|&j| {
+                    match kind(j) {
+                        Some(TokenKind::Punct('[')) => depth += 1,
+                        Some(TokenKind::Punct(']')) => depth -= 1,
+                        _ => {}
+                    }
+
+                    depth == 0
+                });
+
+                let alone = end.is_some_and(|end| {
+                    let line_end = code[token.start..]
+                        .find('\n')
+                        .map_or(code.len(), |n| token.start + n);
+
+                    tokens[end].end <= line_end
+                        && tokens.get(end + 1).is_none_or(|next| next.start > line_end)
+                });
+
+                (!alone).then_some("Attribute")
+            }
+            TokenKind::Punct('&')
+                if i > 0
+                    && tokens[i - 1].kind == TokenKind::Ident
+                    && kind(i + 1) == Some(TokenKind::Ident) =>
+            {
+                let range = type_around(i);
+
+                match is_declared(&range) {
+                    true if range.clone().any(|j| text(j) == "(") => {
+                        Some("Disjunctive normal form type")
+                    }
+                    true => Some("Intersection type"),
+                    false => None,
+                }
+            }
+            TokenKind::Punct('(')
+                if [1, 2, 3].map(|n| text(i + n)) == ["."; 3] && text(i + 4) == ")" =>
+            {
+                Some("First-class callable syntax")
+            }
+            // The first `|` of types like `int|string $value` and `): int|string`
+            TokenKind::Punct('|')
+                if kind(i + 1) == Some(TokenKind::Ident)
+                    && i > 0
+                    && tokens[i - 1].kind == TokenKind::Ident
+                    && previous(2) != "|" =>
+            {
+                let is_type = |j: usize| {
+                    matches!(
+                        kind(j),
+                        Some(TokenKind::Ident | TokenKind::Punct('|' | '\\' | '?'))
+                    )
+                };
+
+                let start = (0..i).rev().find(|&j| !is_type(j)).map_or(0, |j| j + 1);
+                let end = (i..tokens.len())
+                    .find(|&j| !is_type(j))
+                    .unwrap_or(tokens.len());
+
+                // Multi-catch has been around since PHP 7.1
+                let caught = start >= 2 && text(start - 1) == "(" && text(start - 2) == "catch";
+
+                let union = !caught && kind(end) == Some(TokenKind::Variable)
+                    || start >= 2 && text(start - 1) == ":" && text(start - 2) == ")";
+
+                union.then_some("Union type")
+            }
+            _ => None,
+        };
+
+        features.extend(feature.map(|feature| (token.start, feature)));
+    }
+
+    features
+}
+
+/// Finds the first syntax error of some code, Java syntax left in it
+/// included, as well as the features too new for the target version.
+pub fn validate(code: &str, version: Version) -> Vec<SyntaxError> {
+    let tokens = match lex(code) {
+        Ok(tokens) => tokens,
+        Err(e) => return vec![e],
+    };
+
+    let mut errors = Vec::new();
+
+    if tokens.first().map(|token| token.kind) != Some(TokenKind::OpenTag) {
+        errors.push(SyntaxError::new(
+            code,
+            0,
+            "Expected `<?php` at the start of the file".to_owned(),
+        ));
+    }
+
+    let parser = Parser {
+        code,
+        tokens: &tokens,
+        pos: 0,
+    };
+
+    errors.extend(parser.file().err());
+
+    for (offset, feature) in features(code, &tokens) {
+        let since = FEATURES
+            .iter()
+            .find_map(|(name, since)| (*name == feature).then_some(*since))
+            .unwrap_or_default();
+
+        if since > version {
+            errors.push(SyntaxError::new(
+                code,
+                offset,
+                format!(
+                    "{} needs PHP {} or later, the target is PHP {}",
+                    feature, since, version
+                ),
+            ));
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(code: &str) -> Vec<String> {
+        validate(code, Version::V8_3)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn valid() {
+        let code = r#"<?php
+
+declare(strict_types=1);
+
+namespace App;
+
+use App\{Foo, Bar as Baz};
+
+#[Attribute]
+final readonly class Point implements \JsonSerializable
+{
+    use Helpers {
+        hello as protected greet;
+    }
+
+    public const int ORIGIN = 0;
+    private static ?array $cache = null;
+
+    public function __construct(private int $x = 0, string ...$rest)
+    {
+        parent::__construct(...$rest);
+    }
+
+    public function jsonSerialize(): mixed
+    {
+        [$a, [, $b]] = $this->pairs();
+        $f = static fn(int $n): int => $n ** 2;
+        $g = strlen(...);
+        self::$cache['x'] ??= [];
+
+        return match (true) {
+            $a > 1, $b < 0 => $a ?: $b ?: null,
+            default => throw new \LogicException("No {$this->x}"),
+        };
+    }
+}
+
+enum Suit: string
+{
+    case Hearts = 'H';
+}
+
+foreach ($points as $key => &$point):
+    echo $key, $point?->x;
+endforeach;
+?>
+<p><?= $title ?></p>
+"#;
+
+        assert_eq!(errors(code), Vec::<String>::new());
+    }
+
+    #[test]
+    fn missing_semicolon() {
+        assert_eq!(
+            errors("<?php\n$a = 1\n"),
+            ["3:1: Unexpected end of file, expected `;`"]
+        );
+    }
+
+    #[test]
+    fn missing_operand() {
+        assert_eq!(
+            errors("<?php\nreturn $a + ;\n"),
+            ["2:13: Unexpected `;`, expected an expression"]
+        );
+    }
+
+    #[test]
+    fn unbalanced() {
+        assert_eq!(
+            errors("<?php\nif ($a) {\n    f($a];\n}\n"),
+            ["3:9: Expected `)` to close `(` at 3:6, found `]`"]
+        );
+    }
+
+    #[test]
+    fn java_syntax() {
+        assert_eq!(
+            errors("<?php\nList<String> $x = [];\n"),
+            ["2:1: Java generics `List<`, PHP types take no type parameters"]
+        );
+
+        assert_eq!(
+            errors("<?php\n$s = (String) $o;\n"),
+            ["2:6: Java-style cast `(String)`, PHP casts are lowercase, e.g. `(string)`"]
+        );
+
+        assert_eq!(
+            errors("<?php\n$s = (Invoice) $o;\n"),
+            ["2:6: Java-style cast `(Invoice)`, PHP only casts to scalar types, arrays and objects"]
+        );
+
+        assert_eq!(
+            errors("<?php\nint count = 0;\n"),
+            ["2:1: Java-style declaration `int count`, variables need a `$`"]
+        );
+
+        assert_eq!(
+            errors("<?php\nclass A {\n    @Override\n    public function f() {}\n}\n"),
+            ["3:5: Java annotation `@Override`"]
+        );
+
+        assert_eq!(
+            errors("<?php\nclass A {\n    public void f() {}\n}\n"),
+            ["3:17: Java-style method declaration `void f(`, methods are declared with `function`"]
+        );
+    }
+
+    /// The features some code uses, checking that it is valid otherwise.
+    fn used(code: &str) -> Vec<&'static str> {
+        let code = format!("<?php\n{}\n", code);
+        let tokens = lex(&code).unwrap();
+
+        assert!(
+            Parser {
+                code: &code,
+                tokens: &tokens,
+                pos: 0
+            }
+            .file()
+            .is_ok(),
+            "{}",
+            code
+        );
+
+        features(&code, &tokens)
+            .into_iter()
+            .map(|(_, feature)| feature)
+            .collect()
+    }
+
+    fn since(feature: &str) -> Version {
+        FEATURES
+            .iter()
+            .find_map(|(name, since)| (*name == feature).then_some(*since))
+            .unwrap()
+    }
+
+    #[test]
+    fn versions() {
+        let code = "<?php\nenum Suit {}\n";
+
+        assert!(validate(code, Version::V8_1).is_empty());
+        assert_eq!(
+            validate(code, Version::V8_0)[0].message,
+            "Enum needs PHP 8.1 or later, the target is PHP 8.0"
+        );
+    }
+
+    #[test]
+    fn attributes() {
+        let code = "#[Pure] function f() {}";
+        assert_eq!(used(code), ["Attribute"]);
+        assert_eq!(since("Attribute"), Version::V8_0);
+
+        let code = "#[Route(\n    path: '/',\n)]\nfunction f() {}";
+        assert_eq!(used(code), ["Attribute", "Named argument"]);
+
+        // Read as a comment by older versions
+        assert!(used("#[Pure]\nfunction f() {}").is_empty());
+    }
+
+    #[test]
+    fn readonly() {
+        assert_eq!(
+            used("class A { public readonly int $a; }"),
+            ["Readonly property"]
+        );
+        assert_eq!(used("readonly class A {}"), ["Readonly class"]);
+        assert!(used("class ReadOnly extends A { const READONLY = 0; }").is_empty());
+        assert!(used("function f(\\A\\ReadOnly $a) {}").is_empty());
+    }
+
+    #[test]
+    fn catch_without_variable() {
+        assert_eq!(used("try {} catch (A|B) {}"), ["Catch without a variable"]);
+        assert_eq!(since("Catch without a variable"), Version::V8_0);
+        assert!(used("try {} catch (A|B $e) {}").is_empty());
+    }
+
+    #[test]
+    fn throw_expression() {
+        for code in [
+            "$a = $b ?? throw new E();",
+            "$f = fn() => throw new E();",
+            "$a = $b ?: throw new E();",
+            "$a ? f() : throw new E();",
+            "$a or throw new E();",
+        ] {
+            assert_eq!(used(code), ["`throw` expression"], "{}", code);
+        }
+
+        assert_eq!(since("`throw` expression"), Version::V8_0);
+
+        for code in [
+            "throw new E();",
+            "if ($a) throw new E();",
+            "switch ($a) { default: throw new E(); }",
+        ] {
+            assert!(used(code).is_empty(), "{}", code);
+        }
+    }
+
+    #[test]
+    fn new_expression() {
+        assert_eq!(
+            used("$a = new ($prefix . 'Class')();"),
+            ["`new` with an arbitrary expression"]
+        );
+        assert_eq!(since("`new` with an arbitrary expression"), Version::V8_0);
+        assert!(used("$a = new $class();").is_empty());
+    }
+
+    #[test]
+    fn intersection_types() {
+        for code in [
+            "function f(A&B $a) {}",
+            "function f(A&B &$a) {}",
+            "function f(A&B ...$a) {}",
+            "function f(): A&B {}",
+            "class C { private A&B $a; }",
+        ] {
+            assert_eq!(used(code), ["Intersection type"], "{}", code);
+        }
+
+        assert_eq!(since("Intersection type"), Version::V8_1);
+        assert!(used("function f(A &$a) { return A & B; }").is_empty());
+    }
+
+    #[test]
+    fn octal_literals() {
+        assert_eq!(used("$a = 0o17;"), ["Explicit octal literal `0o`"]);
+        assert_eq!(since("Explicit octal literal `0o`"), Version::V8_1);
+        assert!(used("$a = 017;").is_empty());
+    }
+
+    #[test]
+    fn dnf_types() {
+        for code in [
+            "function f((A&B)|null $a) {}",
+            "function f(int|(A&B) $a) {}",
+            "function f(): (A&B)|null {}",
+            "class C { public (A&B)|null $a; }",
+        ] {
+            assert_eq!(used(code), ["Disjunctive normal form type"], "{}", code);
+        }
+
+        assert_eq!(since("Disjunctive normal form type"), Version::V8_2);
+        assert!(used("$a = (A & B) | $c;").is_empty());
+    }
+}
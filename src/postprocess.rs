@@ -0,0 +1,267 @@
+/// Code extracted from a model response, along with the prose around it.
+#[derive(Debug, Default)]
+pub struct Extracted {
+    pub code: String,
+    pub notes: Vec<String>,
+}
+
+/// Lines a PHP file or class body can start with, used to tell code from
+/// prose when the response has no code fences.
+const CODE_STARTS: &[&str] = &[
+    "<?php",
+    "namespace ",
+    "use ",
+    "declare(",
+    "require",
+    "include",
+    "class ",
+    "interface ",
+    "trait ",
+    "enum ",
+    "abstract ",
+    "final ",
+    "readonly ",
+    "function ",
+    "public ",
+    "protected ",
+    "private ",
+    "static ",
+    "const ",
+    "/*",
+    "//",
+    "#[",
+    "$",
+    "}",
+];
+
+fn is_code_start(line: &str) -> bool {
+    let line = line.trim_start();
+    CODE_STARTS.iter().any(|start| line.starts_with(start))
+}
+
+/// Whether a line can end PHP code, as opposed to a sentence.
+fn is_code_end(line: &str) -> bool {
+    let line = line.trim_end();
+    line.ends_with(['}', ';', '{']) || line.ends_with("*/") || line == "?>"
+}
+
+fn push_note(notes: &mut Vec<String>, text: &str) {
+    let text = text.trim();
+
+    if !text.is_empty() {
+        notes.push(text.to_owned());
+    }
+}
+
+/// Separates the PHP code of a response from the explanations around it,
+/// taking the contents of `php` or untagged code fences if there are any.
+pub fn extract(text: &str) -> Extracted {
+    let mut extracted = Extracted::default();
+    let mut blocks = Vec::new();
+    let mut prose = String::new();
+    // Language and contents of the fence being read
+    let mut fence: Option<(&str, String)> = None;
+
+    for line in text.lines() {
+        match (&mut fence, line.trim_start().strip_prefix("```")) {
+            (None, Some(lang)) => {
+                push_note(&mut extracted.notes, &std::mem::take(&mut prose));
+                fence = Some((lang.trim(), String::new()));
+            }
+            (Some(_), Some(_)) => {
+                let (lang, block) = fence.take().unwrap();
+
+                match lang {
+                    "" | "php" => blocks.push(block),
+                    _ => push_note(&mut extracted.notes, &format!("```{}\n{}```", lang, block)),
+                }
+            }
+            (Some((_, block)), None) => {
+                block.push_str(line);
+                block.push('\n');
+            }
+            (None, None) => {
+                prose.push_str(line);
+                prose.push('\n');
+            }
+        }
+    }
+
+    // A fence left open by a truncated response still holds code
+    if let Some((_, block)) = fence {
+        blocks.push(block);
+    }
+
+    if !blocks.is_empty() {
+        push_note(&mut extracted.notes, &prose);
+        extracted.code = blocks.join("\n");
+        return extracted;
+    }
+
+    let lines = prose.lines().collect::<Vec<_>>();
+
+    let Some(start) = lines.iter().position(|line| is_code_start(line)) else {
+        // Better to keep something that does not look like code than nothing
+        extracted.code = prose;
+        return extracted;
+    };
+
+    let end = lines
+        .iter()
+        .rposition(|line| is_code_end(line))
+        .filter(|&end| end >= start)
+        .map_or(lines.len(), |end| end + 1);
+
+    push_note(&mut extracted.notes, &lines[..start].join("\n"));
+    extracted.code = lines[start..end].join("\n");
+    push_note(&mut extracted.notes, &lines[end..].join("\n"));
+
+    extracted
+}
+
+fn is_namespace(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("namespace ") && line.ends_with(';')
+}
+
+fn is_strict_types(line: &str) -> bool {
+    line.trim().replace(' ', "") == "declare(strict_types=1);"
+}
+
+/// Gives code exactly one leading `<?php` tag, no closing tag, the given
+/// namespace and optionally a strict types declaration, in the order PHP
+/// requires them.
+pub fn normalize(code: &str, strict_types: bool, namespace: Option<&str>) -> String {
+    let mut code = code.trim();
+
+    while let Some(rest) = code.strip_prefix("<?php") {
+        code = rest.trim_start();
+    }
+
+    let code = code.strip_suffix("?>").unwrap_or(code).trim_end();
+    let lines = code.lines().collect::<Vec<_>>();
+    let strict_types = strict_types || lines.iter().any(|line| is_strict_types(line));
+
+    let namespace = match namespace {
+        Some(namespace) => Some(format!("namespace {};", namespace)),
+        None => lines
+            .iter()
+            .find(|line| is_namespace(line))
+            .map(|line| line.trim().to_owned()),
+    };
+
+    // Tags repeated where responses or chunks were joined
+    let body = lines
+        .into_iter()
+        .filter(|line| line.trim() != "<?php" && !is_strict_types(line) && !is_namespace(line))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut header = String::from("<?php\n\n");
+
+    if strict_types {
+        header.push_str("declare(strict_types=1);\n\n");
+    }
+
+    if let Some(namespace) = namespace {
+        header.push_str(&namespace);
+        header.push_str("\n\n");
+    }
+
+    format!("{}{}\n", header, body.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn several_fences() {
+        let extracted = extract(
+            "Here is the class.\n\n```php\n<?php\nclass A {}\n```\n\nAnd a helper:\n\n\
+            ```php\nfunction f() {}\n```\n\n```json\n{\"a\": 1}\n```\nHope this helps.\n",
+        );
+
+        assert_eq!(extracted.code, "<?php\nclass A {}\n\nfunction f() {}\n");
+        assert_eq!(
+            extracted.notes,
+            [
+                "Here is the class.",
+                "And a helper:",
+                "```json\n{\"a\": 1}\n```",
+                "Hope this helps.",
+            ]
+        );
+    }
+
+    #[test]
+    fn untagged_fence() {
+        let extracted = extract("```\n$a = 1;\n```");
+        assert_eq!(extracted.code, "$a = 1;\n");
+        assert!(extracted.notes.is_empty());
+    }
+
+    #[test]
+    fn truncated_fence() {
+        let extracted = extract("Converted:\n```php\nclass A {\n");
+        assert_eq!(extracted.code, "class A {\n");
+        assert_eq!(extracted.notes, ["Converted:"]);
+    }
+
+    #[test]
+    fn prose_around_unfenced_code() {
+        let extracted =
+            extract("Sure, here it is:\n<?php\n\nclass A\n{\n}\nThis class does nothing.\n");
+
+        assert_eq!(extracted.code, "<?php\n\nclass A\n{\n}");
+        assert_eq!(
+            extracted.notes,
+            ["Sure, here it is:", "This class does nothing."]
+        );
+    }
+
+    #[test]
+    fn no_code() {
+        let extracted = extract("I cannot convert this file.");
+        assert_eq!(extracted.code, "I cannot convert this file.\n");
+        assert!(extracted.notes.is_empty());
+    }
+
+    #[test]
+    fn existing_strict_types() {
+        let code = "<?php\ndeclare(strict_types = 1);\nclass A {}\n?>";
+        assert_eq!(
+            normalize(code, false, None),
+            "<?php\n\ndeclare(strict_types=1);\n\nclass A {}\n"
+        );
+        assert_eq!(normalize(code, true, None), normalize(code, false, None));
+        assert_eq!(
+            normalize("class A {}", false, None),
+            "<?php\n\nclass A {}\n"
+        );
+    }
+
+    #[test]
+    fn namespace() {
+        let code = "<?php\n\nnamespace Old\\Place;\n\nuse Foo\\Bar;\n\nclass A {}";
+
+        assert_eq!(
+            normalize(code, false, Some("App\\Billing")),
+            "<?php\n\nnamespace App\\Billing;\n\nuse Foo\\Bar;\n\nclass A {}\n"
+        );
+        assert_eq!(
+            normalize(code, true, None),
+            "<?php\n\ndeclare(strict_types=1);\n\nnamespace Old\\Place;\n\nuse Foo\\Bar;\n\n\
+            class A {}\n"
+        );
+    }
+
+    #[test]
+    fn joined_chunks() {
+        let code = "<?php\nnamespace App;\nclass A {\n<?php\nnamespace App;\n    public $a;\n}";
+        assert_eq!(
+            normalize(code, false, Some("App")),
+            "<?php\n\nnamespace App;\n\nclass A {\n    public $a;\n}\n"
+        );
+    }
+}
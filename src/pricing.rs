@@ -0,0 +1,130 @@
+use color_eyre::{eyre::Context, Result};
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// Price in USD per 1K tokens.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Price {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+impl Price {
+    pub fn cost(&self, prompt_tokens: usize, completion_tokens: usize) -> f64 {
+        (prompt_tokens as f64 * self.prompt + completion_tokens as f64 * self.completion) / 1000.0
+    }
+}
+
+/// Prices by model name; dated snapshots are priced like the model they
+/// start with.
+#[derive(Debug)]
+pub struct Pricing(pub BTreeMap<String, Price>);
+
+impl Default for Pricing {
+    fn default() -> Self {
+        Self(
+            [
+                ("gpt-3.5-turbo", 0.0005, 0.0015),
+                ("gpt-3.5-turbo-16k", 0.003, 0.004),
+                ("gpt-3.5-turbo-instruct", 0.0015, 0.002),
+                ("gpt-4", 0.03, 0.06),
+                ("gpt-4-32k", 0.06, 0.12),
+                ("gpt-4-0125-preview", 0.01, 0.03),
+                ("gpt-4-1106-preview", 0.01, 0.03),
+                ("gpt-4-turbo", 0.01, 0.03),
+                ("gpt-4.1", 0.002, 0.008),
+                ("gpt-4.1-mini", 0.0004, 0.0016),
+                ("gpt-4.1-nano", 0.0001, 0.0004),
+                ("gpt-4o", 0.0025, 0.01),
+                ("gpt-4o-mini", 0.00015, 0.0006),
+                ("o1", 0.015, 0.06),
+                ("o1-mini", 0.0011, 0.0044),
+                ("o3-mini", 0.0011, 0.0044),
+                ("text-davinci-003", 0.02, 0.02),
+            ]
+            .into_iter()
+            .map(|(model, prompt, completion)| (model.to_owned(), Price { prompt, completion }))
+            .collect(),
+        )
+    }
+}
+
+impl Pricing {
+    /// Adds or replaces prices with those of a JSON file of the form
+    /// `{"model": {"prompt": 0.001, "completion": 0.002}}`.
+    pub fn extend_from_file(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+
+        let content = fs::read_to_string(path).wrap_err_with(|| format!("{}", path.display()))?;
+
+        let prices: BTreeMap<String, Price> =
+            serde_json::from_str(&content).wrap_err_with(|| format!("{}", path.display()))?;
+
+        self.0.extend(prices);
+
+        Ok(())
+    }
+
+    /// Name of the entry pricing a model, the longest one it starts with.
+    pub fn find(&self, model: &str) -> Option<&str> {
+        self.0
+            .keys()
+            .filter(|name| model.starts_with(name.as_str()))
+            .max_by_key(|name| name.len())
+            .map(String::as_str)
+    }
+
+    /// Like [`Pricing::find`], warning when the model is only priced like
+    /// another one it starts with.
+    pub fn select(&self, model: &str) -> Option<&str> {
+        let name = self.find(model);
+
+        if let Some(name) = name.filter(|name| *name != model) {
+            eprintln!(
+                "No price known for model {}, priced like {}, see --pricing",
+                model, name
+            );
+        }
+
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_prefix() {
+        let pricing = Pricing::default();
+
+        assert_eq!(pricing.find("gpt-4"), Some("gpt-4"));
+        assert_eq!(pricing.find("gpt-4-0613"), Some("gpt-4"));
+        assert_eq!(pricing.find("gpt-4o"), Some("gpt-4o"));
+        assert_eq!(pricing.find("gpt-4o-2024-08-06"), Some("gpt-4o"));
+        assert_eq!(pricing.find("gpt-4o-mini-2024-07-18"), Some("gpt-4o-mini"));
+        assert_eq!(pricing.find("gpt-4-turbo-2024-04-09"), Some("gpt-4-turbo"));
+        assert_eq!(pricing.find("gpt-4.1-nano"), Some("gpt-4.1-nano"));
+        assert_eq!(pricing.find("o1-mini"), Some("o1-mini"));
+        assert_eq!(pricing.find("llama3"), None);
+    }
+
+    #[test]
+    fn prices_from_file() {
+        let path =
+            std::env::temp_dir().join(format!("java-to-php-pricing-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{"gpt-4": {"prompt": 0.01, "completion": 0.02}, "llama3": {"prompt": 0, "completion": 0}}"#,
+        )
+        .unwrap();
+
+        let mut pricing = Pricing::default();
+        pricing.extend_from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(pricing.0["gpt-4"].cost(1000, 500), 0.02);
+        assert_eq!(pricing.find("llama3:8b"), Some("llama3"));
+        assert_eq!(pricing.0["gpt-4o"].cost(2000, 1000), 0.015);
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{
+    backend::Prompt,
+    overload::{self, Rename, Strategy},
+    php::{Version, FEATURES},
+    template::Template,
+};
+use std::{collections::BTreeMap, path::Path};
+
+const SYSTEM: &str = "Convert the following Java code to PHP. \
+Preserve the structure, names and behavior of the original code. \
+Respond with the PHP code only, starting with the <?php tag.";
+
+const EXAMPLE_JAVA: &str = r#"public class Greeter {
+    private final String name;
+
+    public Greeter(String name) {
+        this.name = name;
+    }
+
+    public String greet() {
+        return "Hello, " + name + "!";
+    }
+}"#;
+
+const EXAMPLE_PHP: &str = r#"<?php
+
+class Greeter
+{
+    private string $name;
+
+    public function __construct(string $name)
+    {
+        $this->name = $name;
+    }
+
+    public function greet(): string
+    {
+        return "Hello, " . $this->name . "!";
+    }
+}"#;
+
+/// Template of the conversion requests, unless another one is given.
+pub const TEMPLATE: &str =
+    "{{related}}{{#if glossary}}Glossary of the project:\n{{glossary}}\n{{/if}}Java:\n{{java}}";
+
+fn java(source: &str) -> String {
+    format!("Java:\n{}", source)
+}
+
+/// What the code may use of the language, given the version it must run on.
+fn target(version: Version) -> String {
+    let mut target = format!("The code must run on PHP {}", version);
+
+    let unsupported = FEATURES
+        .iter()
+        .filter(|(_, since)| *since > version)
+        .map(|(feature, _)| {
+            let mut chars = feature.chars();
+
+            chars
+                .next()
+                .map(|c| c.to_lowercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    match unsupported.is_empty() {
+        true => target.push('.'),
+        false => target.push_str(&format!(
+            ", so it must not use any {}.",
+            unsupported.join(", ")
+        )),
+    }
+
+    if version >= Version::V8_0 {
+        target.push_str(" Use `match` and constructor property promotion where they fit.");
+    }
+
+    target.push_str(match version >= Version::V8_1 {
+        true => " Java enums become enums and final fields readonly properties.",
+        false => " Java enums become classes with a constant per enum constant.",
+    });
+
+    target
+}
+
+/// What a file is converted with besides its source.
+#[derive(Debug)]
+pub struct Context<'a> {
+    /// Path of the file, relative to the source
+    pub path: &'a Path,
+    pub package: Option<String>,
+    /// Namespace the code must be declared in
+    pub namespace: Option<&'a str>,
+    /// Java signatures of the project types the file refers to
+    pub related: Vec<&'a str>,
+    /// PHP signatures of the converted files declaring some of them
+    pub converted: Vec<String>,
+    /// PHP names of the overloads it declares or calls
+    pub renames: Vec<&'a Rename>,
+    pub overloads: Strategy,
+    pub php_version: Version,
+    pub template: &'a Template,
+    /// Terms of the project and how to translate them
+    pub glossary: Option<&'a str>,
+}
+
+impl Context<'_> {
+    /// The instructions, along with the target version and the namespace the
+    /// code must be declared in.
+    fn system(&self, instructions: &str) -> String {
+        let instructions = format!("{} {}", instructions, target(self.php_version));
+
+        match self.namespace {
+            Some(namespace) => format!("{} Declare the namespace `{}`.", instructions, namespace),
+            None => instructions,
+        }
+    }
+
+    /// Signatures of related types to put before the code, if any.
+    fn related(&self) -> String {
+        let mut related = String::new();
+
+        if !self.converted.is_empty() {
+            related.push_str(&format!(
+                "Already converted project types it refers to, whose signatures must be used as they are:\n{}\n",
+                self.converted.join("\n")
+            ));
+        }
+
+        if !self.related.is_empty() {
+            related.push_str(&format!(
+                "Signatures of the project types it refers to, converted the same way:\n{}\n",
+                self.related.join("\n")
+            ));
+        }
+
+        if !self.renames.is_empty() {
+            related.push_str(&overload::instructions(self.overloads, &self.renames));
+            related.push('\n');
+        }
+
+        related
+    }
+
+    /// The request to convert some code, built from the template.
+    fn java(&self, source: &str) -> String {
+        let variables = BTreeMap::from([
+            ("java", source.to_owned()),
+            ("path", self.path.display().to_string()),
+            ("package", self.package.clone().unwrap_or_default()),
+            ("namespace", self.namespace.unwrap_or_default().to_owned()),
+            ("php_version", self.php_version.to_string()),
+            ("related", self.related()),
+            ("glossary", self.glossary.unwrap_or_default().to_owned()),
+        ]);
+
+        self.template.render(&variables)
+    }
+}
+
+pub fn conversion(source: &str, context: &Context) -> Prompt {
+    Prompt::new(context.system(SYSTEM))
+        .example(java(EXAMPLE_JAVA), EXAMPLE_PHP)
+        .user(context.java(source))
+}
+
+/// Converts a file whose methods were cut out to be converted separately.
+pub fn shell(source: &str, type_name: &str, context: &Context) -> Prompt {
+    Prompt::new(format!(
+        "{} The methods of `{}` have been removed and will be converted separately, \
+        so do not add any methods to it.",
+        context.system(SYSTEM),
+        type_name
+    ))
+    .user(context.java(source))
+}
+
+pub fn members(skeleton: &str, type_name: &str, chunk: &str, context: &Context) -> Prompt {
+    Prompt::new(format!(
+        "Convert the following methods of the Java class `{}` to PHP. \
+        Preserve the names and behavior of the original code. \
+        Respond with the PHP methods only, without the <?php tag and without the enclosing class. {}",
+        type_name,
+        target(context.php_version)
+    ))
+    .user(context.java(&format!(
+        "The class, with method bodies elided, for context:\n{}\n\nMethods to convert:\n{}",
+        skeleton, chunk
+    )))
+}
+
+/// Asks to fix the syntax errors of a converted file, keeping to the target
+/// version and namespace of the conversion.
+pub fn repair(source: &str, php: &str, errors: &str, context: &Context) -> Prompt {
+    Prompt::new(context.system(
+        "The following PHP code was converted from the Java code before it \
+        and has the errors listed after it. \
+        Fix the errors without otherwise changing the code. \
+        Respond with the corrected PHP code only, starting with the <?php tag.",
+    ))
+    .user(format!(
+        "{}\n\nPHP:\n{}\n\nErrors (line:column):\n{}",
+        java(source),
+        php,
+        errors
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context<'a>(template: &'a Template, renames: Vec<&'a Rename>) -> Context<'a> {
+        Context {
+            path: Path::new("billing/Invoice.java"),
+            package: Some("com.acme.billing".to_owned()),
+            namespace: Some("Acme\\Billing"),
+            related: vec!["class Customer {}"],
+            converted: Vec::new(),
+            renames,
+            overloads: Strategy::Suffix,
+            php_version: Version::V7_4,
+            template,
+            glossary: Some("Invoice: Rechnung"),
+        }
+    }
+
+    #[test]
+    fn repair_keeps_to_the_target() {
+        let template = TEMPLATE.parse().unwrap();
+        let prompt = repair(
+            "class A {}",
+            "<?php\nclass A {",
+            "2:10: Unclosed `{`",
+            &context(&template, Vec::new()),
+        );
+        let system = &prompt.messages[0].content;
+
+        assert!(system.contains("The code must run on PHP 7.4, so it must not use any "));
+        assert!(system.ends_with("Declare the namespace `Acme\\Billing`."));
+        assert_eq!(
+            prompt.messages.last().unwrap().content,
+            "Java:\nclass A {}\n\nPHP:\n<?php\nclass A {\n\nErrors (line:column):\n2:10: Unclosed `{`"
+        );
+    }
+
+    #[test]
+    fn members_use_the_template() {
+        let template = "Legacy code of {{namespace}}:\n{{java}}".parse().unwrap();
+        let prompt = members(
+            "class Invoice {}",
+            "Invoice",
+            "void a() {}",
+            &context(&template, Vec::new()),
+        );
+
+        assert_eq!(
+            prompt.messages.last().unwrap().content,
+            "Legacy code of Acme\\Billing:\nThe class, with method bodies elided, for context:\n\
+            class Invoice {}\n\nMethods to convert:\nvoid a() {}"
+        );
+
+        let template = TEMPLATE.parse().unwrap();
+        let add = Rename::new(
+            "Invoice",
+            Some("add"),
+            &["int".to_owned()],
+            Strategy::Suffix,
+        );
+        let prompt = members(
+            "class Invoice {}",
+            "Invoice",
+            "void a() {}",
+            &context(&template, vec![&add]),
+        );
+        let request = &prompt.messages.last().unwrap().content;
+
+        assert!(request.starts_with("Signatures of the project types it refers to"));
+        assert!(request.contains(
+            "`Invoice.add(int)`: `addInt`\nGlossary of the project:\nInvoice: Rechnung\n"
+        ));
+        assert!(request.ends_with(
+            "Java:\nThe class, with method bodies elided, for context:\n\
+            class Invoice {}\n\nMethods to convert:\nvoid a() {}"
+        ));
+    }
+}
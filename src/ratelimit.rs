@@ -0,0 +1,302 @@
+use crate::{
+    backend::{
+        ApiError, Backend, BackendKind, BoxFuture, Completion, ErrorKind, Prompt, RateLimits,
+    },
+    retry, tokens,
+};
+use color_eyre::Result;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::{sleep, Instant};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+struct Entry {
+    time: Instant,
+    requests: u32,
+    /// Corrections of earlier estimates are negative
+    tokens: i64,
+}
+
+#[derive(Default)]
+struct State {
+    /// What was sent during the last minute
+    entries: VecDeque<Entry>,
+    /// Set when the server reports an exhausted limit
+    paused_until: Option<Instant>,
+    server_requests_per_minute: Option<u32>,
+    server_tokens_per_minute: Option<u32>,
+}
+
+/// Client-side sliding window limits, tightened by the limits the server
+/// reports in its response headers.
+pub struct RateLimiter {
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u32>,
+    state: Mutex<State>,
+}
+
+fn min_limit(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_minute: Option<u32>, tokens_per_minute: Option<u32>) -> Self {
+        Self {
+            requests_per_minute,
+            tokens_per_minute,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Waits until a request of about `tokens` tokens fits in the limits and records it.
+    pub async fn acquire(&self, tokens: usize) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+
+                while let Some(entry) = state.entries.front() {
+                    if now.duration_since(entry.time) < WINDOW {
+                        break;
+                    }
+
+                    state.entries.pop_front();
+                }
+
+                match state.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        let requests = state.entries.iter().map(|e| e.requests).sum::<u32>();
+                        let used = state.entries.iter().map(|e| e.tokens).sum::<i64>();
+
+                        let requests_per_minute =
+                            min_limit(self.requests_per_minute, state.server_requests_per_minute);
+
+                        let tokens_per_minute =
+                            min_limit(self.tokens_per_minute, state.server_tokens_per_minute);
+
+                        // A single request larger than the limit is let through on its own
+                        let fits = requests == 0
+                            || requests_per_minute.is_none_or(|limit| requests < limit)
+                                && tokens_per_minute
+                                    .is_none_or(|limit| used + tokens as i64 <= limit as i64);
+
+                        if fits {
+                            state.entries.push_back(Entry {
+                                time: now,
+                                requests: 1,
+                                tokens: tokens as i64,
+                            });
+
+                            return;
+                        }
+
+                        state
+                            .entries
+                            .front()
+                            .map_or(Duration::ZERO, |e| WINDOW - now.duration_since(e.time))
+                    }
+                }
+            };
+
+            sleep(wait.max(Duration::from_millis(10))).await;
+        }
+    }
+
+    /// Replaces the estimate of a finished request with its actual usage and
+    /// takes the limits reported by the server into account.
+    pub fn record(&self, estimate: usize, actual: Option<usize>, limits: &RateLimits) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+
+        if let Some(actual) = actual {
+            state.entries.push_back(Entry {
+                time: now,
+                requests: 0,
+                tokens: actual as i64 - estimate as i64,
+            });
+        }
+
+        state.server_requests_per_minute =
+            limits.limit_requests.or(state.server_requests_per_minute);
+
+        state.server_tokens_per_minute = limits.limit_tokens.or(state.server_tokens_per_minute);
+
+        let exhausted = [
+            (limits.remaining_requests == Some(0), limits.reset_requests),
+            (
+                limits
+                    .remaining_tokens
+                    .is_some_and(|r| (r as usize) < estimate),
+                limits.reset_tokens,
+            ),
+        ];
+
+        for (exhausted, reset) in exhausted {
+            if exhausted {
+                let until = now + reset.unwrap_or(Duration::from_secs(1));
+                state.paused_until = state.paused_until.max(Some(until));
+            }
+        }
+    }
+
+    /// Takes the limits reported with a failed request into account, holding
+    /// back all requests for as long as the server asks when it was throttled.
+    pub fn record_error(&self, estimate: usize, error: &ApiError) {
+        self.record(estimate, None, &error.rate_limits);
+
+        if error.kind == ErrorKind::RateLimited {
+            let limits = &error.rate_limits;
+
+            let delay = error
+                .retry_after
+                .or(limits.reset_requests.max(limits.reset_tokens))
+                .unwrap_or(Duration::from_secs(1))
+                .min(retry::MAX_RETRY_AFTER);
+
+            let mut state = self.state.lock().unwrap();
+            let until = Instant::now() + delay;
+            state.paused_until = state.paused_until.max(Some(until));
+        }
+    }
+}
+
+pub struct Throttled {
+    pub inner: Arc<dyn Backend>,
+    pub limiter: RateLimiter,
+}
+
+impl Backend for Throttled {
+    fn kind(&self) -> BackendKind {
+        self.inner.kind()
+    }
+
+    fn url(&self) -> &str {
+        self.inner.url()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn complete<'a>(&'a self, prompt: &'a Prompt) -> BoxFuture<'a, Result<Completion>> {
+        Box::pin(async move {
+            let estimate = tokens::count_prompt(self.model(), prompt);
+            self.limiter.acquire(estimate).await;
+            let completion = match self.inner.complete(prompt).await {
+                Ok(completion) => completion,
+                Err(e) => {
+                    if let Some(error) = e.downcast_ref::<ApiError>() {
+                        self.limiter.record_error(estimate, error);
+                    }
+
+                    return Err(e);
+                }
+            };
+
+            let actual = completion.usage.total_tokens;
+
+            self.limiter.record(
+                estimate,
+                (actual > 0).then_some(actual),
+                &completion.rate_limits,
+            );
+
+            Ok(completion)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::timeout;
+
+    /// Whether a request of some tokens is let through right away.
+    async fn passes(limiter: &RateLimiter, tokens: usize) -> bool {
+        timeout(Duration::from_millis(50), limiter.acquire(tokens))
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn requests_per_minute() {
+        let limiter = RateLimiter::new(Some(2), None);
+
+        assert!(passes(&limiter, 10).await);
+        assert!(passes(&limiter, 10).await);
+        assert!(!passes(&limiter, 10).await);
+    }
+
+    #[tokio::test]
+    async fn tokens_per_minute() {
+        let limiter = RateLimiter::new(None, Some(100));
+
+        assert!(passes(&limiter, 60).await);
+        assert!(!passes(&limiter, 60).await);
+
+        // Estimates are corrected with the actual usage
+        limiter.record(60, Some(30), &RateLimits::default());
+        assert!(passes(&limiter, 60).await);
+
+        // Larger requests than the limit still go through on their own
+        assert!(passes(&RateLimiter::new(None, Some(100)), 500).await);
+    }
+
+    #[tokio::test]
+    async fn server_limits() {
+        let limiter = RateLimiter::new(Some(10), None);
+
+        let limits = RateLimits {
+            limit_requests: Some(1),
+            ..RateLimits::default()
+        };
+
+        limiter.record(0, None, &limits);
+        assert!(passes(&limiter, 10).await);
+        assert!(!passes(&limiter, 10).await);
+
+        let limiter = RateLimiter::new(None, None);
+
+        let limits = RateLimits {
+            remaining_requests: Some(0),
+            reset_requests: Some(Duration::from_secs(20)),
+            ..RateLimits::default()
+        };
+
+        limiter.record(10, Some(10), &limits);
+        assert!(!passes(&limiter, 10).await);
+    }
+
+    #[tokio::test]
+    async fn throttled() {
+        let limiter = RateLimiter::new(None, None);
+
+        let error = |kind, retry_after| ApiError {
+            kind,
+            status: None,
+            message: String::new(),
+            retry_after,
+            rate_limits: RateLimits::default(),
+        };
+
+        limiter.record_error(10, &error(ErrorKind::Server, Some(Duration::from_secs(20))));
+        assert!(passes(&limiter, 10).await);
+
+        limiter.record_error(
+            10,
+            &error(ErrorKind::RateLimited, Some(Duration::from_secs(3600))),
+        );
+        assert!(!passes(&limiter, 10).await);
+
+        let paused_until = limiter.state.lock().unwrap().paused_until.unwrap();
+        assert!(paused_until <= Instant::now() + retry::MAX_RETRY_AFTER);
+    }
+}
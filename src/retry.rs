@@ -0,0 +1,167 @@
+use crate::backend::{ApiError, Backend, BackendKind, BoxFuture, Completion, Prompt};
+use color_eyre::Result;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::Arc,
+    time::Duration,
+};
+
+const BASE_DELAY: Duration = Duration::from_secs(1);
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Longest delay requested by the server that is waited for before retrying.
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// Exponential backoff with jitter, between half and all of the nominal delay.
+fn backoff(attempt: u32) -> Duration {
+    let delay = BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_DELAY);
+
+    let random = RandomState::new().build_hasher().finish();
+    let jitter = (random % 1000) as f64 / 1000.;
+    delay.mul_f64(0.5 + jitter / 2.)
+}
+
+/// Retries transient API errors, honoring the delay requested by the server.
+pub struct Retrying {
+    pub inner: Arc<dyn Backend>,
+    pub max_retries: u32,
+}
+
+impl Backend for Retrying {
+    fn kind(&self) -> BackendKind {
+        self.inner.kind()
+    }
+
+    fn url(&self) -> &str {
+        self.inner.url()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn complete<'a>(&'a self, prompt: &'a Prompt) -> BoxFuture<'a, Result<Completion>> {
+        Box::pin(async move {
+            let mut attempt = 0;
+
+            loop {
+                let e = match self.inner.complete(prompt).await {
+                    Ok(completion) => return Ok(completion),
+                    Err(e) => e,
+                };
+
+                let delay = match e.downcast_ref::<ApiError>() {
+                    Some(error) if error.is_retryable() && attempt < self.max_retries => {
+                        error.retry_after.unwrap_or_else(|| backoff(attempt))
+                    }
+                    _ => return Err(e),
+                };
+
+                // Rather than stall the run without a word
+                if delay > MAX_RETRY_AFTER {
+                    return Err(e.wrap_err(format!(
+                        "The server asked to retry in {}s, more than the {}s waited at most",
+                        delay.as_secs(),
+                        MAX_RETRY_AFTER.as_secs()
+                    )));
+                }
+
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{ErrorKind, RateLimits};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails with the same error every time.
+    struct Failing {
+        kind: ErrorKind,
+        retry_after: Option<Duration>,
+        calls: AtomicU32,
+    }
+
+    impl Backend for Failing {
+        fn kind(&self) -> BackendKind {
+            BackendKind::Compatible
+        }
+
+        fn url(&self) -> &str {
+            "http://localhost"
+        }
+
+        fn model(&self) -> &str {
+            "test"
+        }
+
+        fn complete<'a>(&'a self, _: &'a Prompt) -> BoxFuture<'a, Result<Completion>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+
+            Box::pin(async move {
+                Err(ApiError {
+                    kind: self.kind,
+                    status: None,
+                    message: "failed".to_owned(),
+                    retry_after: self.retry_after,
+                    rate_limits: RateLimits::default(),
+                }
+                .into())
+            })
+        }
+    }
+
+    async fn calls(kind: ErrorKind, retry_after: Option<Duration>) -> (u32, String) {
+        let failing = Arc::new(Failing {
+            kind,
+            retry_after,
+            calls: AtomicU32::new(0),
+        });
+
+        let retrying = Retrying {
+            inner: failing.clone(),
+            max_retries: 2,
+        };
+
+        let e = retrying.complete(&Prompt::new("")).await.unwrap_err();
+        (failing.calls.load(Ordering::SeqCst), e.to_string())
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors() {
+        let delay = Some(Duration::from_millis(1));
+
+        assert_eq!(calls(ErrorKind::RateLimited, delay).await.0, 3);
+        assert_eq!(calls(ErrorKind::Server, delay).await.0, 3);
+        assert_eq!(calls(ErrorKind::InvalidRequest, delay).await.0, 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_long_delays() {
+        let (calls, message) =
+            calls(ErrorKind::RateLimited, Some(Duration::from_secs(86400))).await;
+
+        assert_eq!(calls, 1);
+        assert_eq!(
+            message,
+            "The server asked to retry in 86400s, more than the 300s waited at most"
+        );
+    }
+
+    #[test]
+    fn backoff_grows_up_to_the_maximum() {
+        for attempt in 0..10 {
+            let nominal = BASE_DELAY.saturating_mul(1 << attempt).min(MAX_DELAY);
+            let delay = backoff(attempt);
+
+            assert!(delay >= nominal / 2 && delay <= nominal, "{:?}", delay);
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{
+    java::{self, MemberKind, TypeDecl},
+    php::{self, TokenKind},
+    postprocess,
+};
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// Adds the nested types of a Java type, by name, along with the types they
+/// are nested in.
+fn nested(decl: &TypeDecl, outer: &[String], types: &mut BTreeMap<String, Vec<String>>) {
+    let mut outer = outer.to_owned();
+    outer.push(decl.name.clone());
+
+    for member in &decl.members {
+        if let MemberKind::Type(nested_decl) = &member.kind {
+            types
+                .entry(nested_decl.name.clone())
+                .or_insert_with(|| outer.clone());
+
+            nested(nested_decl, &outer, types);
+        }
+    }
+}
+
+struct Piece<'a> {
+    name: &'a str,
+    span: Range<usize>,
+    /// Types it is nested in within the Java source
+    outer: Vec<String>,
+}
+
+impl Piece<'_> {
+    fn namespace(&self, namespace: Option<&str>) -> Option<String> {
+        let segments = namespace
+            .into_iter()
+            .chain(self.outer.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+
+        (!segments.is_empty()).then(|| segments.join("\\"))
+    }
+
+    fn qualified_name(&self, namespace: Option<&str>) -> String {
+        match self.namespace(namespace) {
+            Some(namespace) => format!("{}\\{}", namespace, self.name),
+            None => self.name.to_owned(),
+        }
+    }
+}
+
+/// Splits converted code declaring several types into one file per type next
+/// to `path`, with the types nested in Java in a sub-namespace named after
+/// their outer types, and `use` statements for the types moved out of each
+/// other's namespace, including the `neighbours` of the package. Returns
+/// `None` for code declaring a single type.
+pub fn split(
+    code: &str,
+    java: &str,
+    path: &Path,
+    neighbours: &[&str],
+) -> Option<Vec<(PathBuf, String)>> {
+    let tokens = php::lex(code).ok()?;
+    let declarations = php::type_declarations(code).ok()?;
+
+    if declarations.len() < 2 {
+        return None;
+    }
+
+    let mut nested_types = BTreeMap::new();
+
+    if let Ok(file) = java::parse(java) {
+        for decl in &file.types {
+            nested(decl, &[], &mut nested_types);
+        }
+
+        for decl in &file.types {
+            nested_types.remove(&decl.name);
+        }
+    }
+
+    let namespace = code.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("namespace ")?
+            .strip_suffix(';')
+            .map(str::trim)
+    });
+
+    let header = code[..declarations[0].1.start].trim_end();
+    let last = declarations.len() - 1;
+
+    let pieces = declarations
+        .iter()
+        .enumerate()
+        .map(|(i, (name, span))| Piece {
+            name,
+            // Statements after the last type stay with it
+            span: match i == last {
+                true => span.start..code.len(),
+                false => span.clone(),
+            },
+            outer: nested_types.get(name).cloned().unwrap_or_default(),
+        })
+        .collect::<Vec<_>>();
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let files = pieces
+        .iter()
+        .map(|piece| {
+            let own_namespace = piece.namespace(namespace);
+
+            let identifiers = tokens
+                .iter()
+                .filter(|token| token.kind == TokenKind::Ident && piece.span.contains(&token.start))
+                .map(|token| &code[token.start..token.end])
+                .collect::<Vec<_>>();
+
+            let mut uses = String::new();
+
+            let others = pieces
+                .iter()
+                .filter(|other| other.name != piece.name)
+                .map(|other| (other.name, other.qualified_name(namespace)));
+
+            let neighbours = match piece.outer.is_empty() {
+                true => Vec::new(),
+                false => neighbours
+                    .iter()
+                    .map(|name| {
+                        let qualified = match namespace {
+                            Some(namespace) => format!("{}\\{}", namespace, name),
+                            None => (*name).to_owned(),
+                        };
+
+                        (*name, qualified)
+                    })
+                    .collect(),
+            };
+
+            for (name, qualified) in others.chain(neighbours) {
+                let same_namespace = qualified.rsplit_once('\\').map(|(namespace, _)| namespace)
+                    == own_namespace.as_deref();
+
+                let statement = format!("use {};", qualified);
+
+                if !same_namespace
+                    && identifiers.contains(&name)
+                    && !header.contains(&statement)
+                    && !uses.contains(&statement)
+                {
+                    uses.push_str(&statement);
+                    uses.push('\n');
+                }
+            }
+
+            let code = format!(
+                "{}\n{}\n{}\n",
+                header,
+                uses,
+                code[piece.span.clone()].trim()
+            );
+
+            let mut path = piece
+                .outer
+                .iter()
+                .fold(dir.to_owned(), |path, outer| path.join(outer));
+
+            path.push(format!("{}.php", piece.name));
+
+            (
+                path,
+                postprocess::normalize(&code, false, own_namespace.as_deref()),
+            )
+        })
+        .collect();
+
+    Some(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JAVA: &str = "package a;
+
+class Outer {
+    static class Inner {
+        Outer outer;
+        Neighbour neighbour;
+    }
+
+    Inner inner;
+    Helper helper;
+}
+
+class Helper {}
+";
+
+    const PHP: &str = "<?php
+
+namespace App;
+
+use Foo\\Bar;
+
+class Outer
+{
+    private Inner $inner;
+    private Helper $helper;
+}
+
+class Inner
+{
+    private Outer $outer;
+    private Neighbour $neighbour;
+}
+
+class Helper
+{
+}
+";
+
+    #[test]
+    fn one_file_per_type() {
+        let files = split(PHP, JAVA, Path::new("out/App/Outer.php"), &["Neighbour"]).unwrap();
+
+        let paths = files
+            .iter()
+            .map(|(path, _)| path.to_str().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            paths,
+            [
+                "out/App/Outer.php",
+                "out/App/Outer/Inner.php",
+                "out/App/Helper.php"
+            ]
+        );
+
+        assert_eq!(
+            files[0].1,
+            "<?php\n\nnamespace App;\n\nuse Foo\\Bar;\nuse App\\Outer\\Inner;\n\nclass Outer\n{\n    \
+            private Inner $inner;\n    private Helper $helper;\n}\n"
+        );
+        assert_eq!(
+            files[1].1,
+            "<?php\n\nnamespace App\\Outer;\n\nuse Foo\\Bar;\nuse App\\Outer;\nuse App\\Neighbour;\n\n\
+            class Inner\n{\n    private Outer $outer;\n    private Neighbour $neighbour;\n}\n"
+        );
+        assert_eq!(
+            files[2].1,
+            "<?php\n\nnamespace App;\n\nuse Foo\\Bar;\n\nclass Helper\n{\n}\n"
+        );
+    }
+
+    #[test]
+    fn single_type() {
+        let php = "<?php\n\nclass Helper\n{\n}\n";
+        assert!(split(php, JAVA, Path::new("Helper.php"), &[]).is_none());
+        assert!(split("<?php\nclass {", JAVA, Path::new("Helper.php"), &[]).is_none());
+    }
+}
@@ -0,0 +1,714 @@
+use crate::{
+    java::{self, JavaFile, MemberKind, TypeDecl, TypeKind},
+    overload::{Rename, Strategy},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+fn keyword(kind: TypeKind) -> &'static str {
+    match kind {
+        TypeKind::Class => "class",
+        TypeKind::Interface => "interface",
+        TypeKind::Enum => "enum",
+        TypeKind::Record => "record",
+        TypeKind::Annotation => "@interface",
+    }
+}
+
+fn is_private(modifiers: &[String]) -> bool {
+    modifiers.iter().any(|modifier| modifier == "private")
+}
+
+/// The non-private API of a type as Java declarations without bodies.
+fn summarize(decl: &TypeDecl, indent: &str, summary: &mut String) {
+    let mut header = decl.modifiers.clone();
+    header.push(keyword(decl.kind).to_owned());
+    header.push(decl.name.clone());
+
+    for (keyword, types) in [("extends", &decl.extends), ("implements", &decl.implements)] {
+        if !types.is_empty() {
+            header.push(format!("{} {}", keyword, types.join(", ")));
+        }
+    }
+
+    summary.push_str(&format!("{}{} {{\n", indent, header.join(" ")));
+
+    for member in &decl.members {
+        match &member.kind {
+            MemberKind::EnumConstants(constants) => {
+                summary.push_str(&format!("{}    {};\n", indent, constants.join(", ")));
+            }
+            MemberKind::Field(field) if !is_private(&field.modifiers) => {
+                summary.push_str(&format!("{}    {}\n", indent, field.declaration));
+            }
+            MemberKind::Method(method) | MemberKind::Constructor(method)
+                if !is_private(&method.modifiers) =>
+            {
+                summary.push_str(&format!("{}    {};\n", indent, method.signature));
+            }
+            MemberKind::Type(nested) if !is_private(&nested.modifiers) => {
+                summarize(nested, &format!("{}    ", indent), summary);
+            }
+            _ => {}
+        }
+    }
+
+    summary.push_str(&format!("{}}}\n", indent));
+}
+
+/// A top-level type of a project.
+#[derive(Debug)]
+struct Type {
+    /// Relative path of the file declaring it
+    file: PathBuf,
+    summary: String,
+}
+
+/// Summaries of the top-level types of a project by qualified name, along with
+/// the PHP names of its overloaded methods.
+#[derive(Debug, Default)]
+pub struct Index {
+    types: BTreeMap<String, Type>,
+    /// By relative path of the file declaring them
+    renames: BTreeMap<PathBuf, Vec<Rename>>,
+    /// Overloaded methods of the types of a file, inherited ones included
+    callable: BTreeMap<PathBuf, Vec<Rename>>,
+    /// Overloaded methods of the types by qualified name, inherited ones included
+    methods: BTreeMap<String, Vec<Rename>>,
+}
+
+impl Index {
+    /// Indexes the files given by relative path and source, leaving out those
+    /// that cannot be parsed.
+    pub fn new<'a>(
+        files: impl IntoIterator<Item = (&'a Path, &'a str)>,
+        strategy: Strategy,
+    ) -> Self {
+        let mut index = Self::default();
+        let mut parsed = Vec::new();
+
+        for (path, source) in files {
+            let Ok(java) = java::parse(source) else {
+                continue;
+            };
+
+            for decl in &java.types {
+                let mut summary = String::new();
+                summarize(decl, "", &mut summary);
+
+                index.types.insert(
+                    qualify(&java, &decl.name),
+                    Type {
+                        file: path.to_owned(),
+                        summary,
+                    },
+                );
+            }
+
+            parsed.push((path, java));
+        }
+
+        (index.renames, index.callable, index.methods) = renames(&parsed, strategy);
+        index
+    }
+
+    /// Names of the top-level types of a package.
+    pub fn package_types(&self, package: Option<&str>) -> Vec<&str> {
+        self.types
+            .keys()
+            .filter_map(|name| match name.rsplit_once('.') {
+                Some((name_package, name)) if Some(name_package) == package => Some(name),
+                None if package.is_none() => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// PHP names of the overloaded methods and constructors a file declares.
+    pub fn renames(&self, file: &Path) -> &[Rename] {
+        self.renames.get(file).map_or(&[], Vec::as_slice)
+    }
+
+    /// PHP names of the overloaded methods of the types a file declares,
+    /// whether declared by them or by their supertypes or subtypes.
+    pub fn callable(&self, file: &Path) -> &[Rename] {
+        self.callable.get(file).map_or(&[], Vec::as_slice)
+    }
+
+    /// PHP names of the overloaded methods of the project types a file refers
+    /// to by simple name, whether declared by them or by their supertypes or
+    /// subtypes, or `None` for a name the file may use for several types.
+    pub fn methods<'a>(&'a self, source: &str) -> impl Fn(&str) -> Option<&'a [Rename]> + 'a {
+        let file = java::parse(source).ok();
+        let names = self.types.keys().chain(self.methods.keys());
+        let mut visible = BTreeMap::<&str, (u8, Option<&str>)>::new();
+
+        for name in names.map(String::as_str).collect::<BTreeSet<_>>() {
+            let simple = name.rsplit('.').next().unwrap_or_default();
+            let top = self.top_level(name);
+            let package = top.rsplit_once('.').map(|(package, _)| package);
+
+            // Types the file declares or imports, then those of its package and
+            // of the packages it imports, then any other as long as it is the only one
+            let rank = match &file {
+                Some(file)
+                    if file
+                        .types
+                        .iter()
+                        .any(|decl| qualify(file, &decl.name) == top)
+                        || file.imports.iter().any(|import| import.path == *name) =>
+                {
+                    0
+                }
+                Some(file) if package == file.package.as_deref() => 1,
+                Some(file)
+                    if file.imports.iter().any(|import| {
+                        package.is_some() && package == import.path.strip_suffix(".*")
+                    }) =>
+                {
+                    2
+                }
+                _ => 3,
+            };
+
+            visible
+                .entry(simple)
+                .and_modify(|(best, found)| {
+                    if rank < *best {
+                        (*best, *found) = (rank, Some(name));
+                    } else if rank == *best {
+                        *found = None;
+                    }
+                })
+                .or_insert((rank, Some(name)));
+        }
+
+        move |name| match visible.get(name) {
+            Some((_, Some(qualified))) => {
+                Some(self.methods.get(*qualified).map_or(&[], Vec::as_slice))
+            }
+            Some((_, None)) => None,
+            None => Some(&[]),
+        }
+    }
+
+    /// Top-level type a qualified name refers to, or belongs to if it names a
+    /// nested type or a static member.
+    fn top_level<'a>(&self, mut name: &'a str) -> &'a str {
+        while !self.types.contains_key(name) {
+            match name.rsplit_once('.') {
+                Some((outer, _)) => name = outer,
+                None => break,
+            }
+        }
+
+        name
+    }
+
+    /// Qualified names of the types a file imports, explicitly or through a
+    /// wildcard import and a reference.
+    fn imported(&self, file: &JavaFile, identifiers: &BTreeSet<&str>) -> Vec<String> {
+        let mut names = Vec::new();
+
+        for import in &file.imports {
+            match import.wildcard {
+                true => {
+                    let package = import.path.trim_end_matches(".*");
+
+                    names.extend(
+                        identifiers
+                            .iter()
+                            .map(|identifier| format!("{}.{}", package, identifier)),
+                    );
+                }
+                false => names.push(self.top_level(&import.path).to_owned()),
+            }
+        }
+
+        names
+    }
+
+    /// Files and summaries of the other project types a file imports or refers
+    /// to by their simple name.
+    pub fn related(&self, source: &str) -> Vec<(&Path, &str)> {
+        let (Ok(file), Ok(identifiers)) = (java::parse(source), java::identifiers(source)) else {
+            return Vec::new();
+        };
+
+        let mut names = self.imported(&file, &identifiers);
+
+        names.extend(
+            identifiers
+                .iter()
+                .filter(|identifier| file.types.iter().all(|decl| decl.name != **identifier))
+                .map(|identifier| qualify(&file, identifier)),
+        );
+
+        let mut related = Vec::new();
+
+        for name in names {
+            if let Some(ty) = self.types.get(&name) {
+                let entry = (ty.file.as_path(), ty.summary.as_str());
+
+                if !related.contains(&entry) {
+                    related.push(entry);
+                }
+            }
+        }
+
+        related
+    }
+
+    /// Files declaring the project types a file imports, extends or implements.
+    pub fn dependencies(&self, source: &str) -> BTreeSet<&Path> {
+        let (Ok(file), Ok(identifiers)) = (java::parse(source), java::identifiers(source)) else {
+            return BTreeSet::new();
+        };
+
+        let mut names = self.imported(&file, &identifiers);
+        let mut decls = file.types.iter().collect::<Vec<_>>();
+
+        while let Some(decl) = decls.pop() {
+            for name in decl.extends.iter().chain(&decl.implements) {
+                // Qualified, or in the same package, possibly nested in another type
+                names.push(self.top_level(name).to_owned());
+                names.push(qualify(&file, name.split('.').next().unwrap_or_default()));
+            }
+
+            decls.extend(decl.members.iter().filter_map(|member| match &member.kind {
+                MemberKind::Type(nested) => Some(nested),
+                _ => None,
+            }));
+        }
+
+        names
+            .iter()
+            .filter_map(|name| self.types.get(name))
+            .map(|ty| ty.file.as_path())
+            .collect()
+    }
+}
+
+fn qualify(file: &JavaFile, name: &str) -> String {
+    match &file.package {
+        Some(package) => format!("{}.{}", package, name),
+        None => name.to_owned(),
+    }
+}
+
+/// A type declaration, nested ones included.
+struct Declaration<'a> {
+    file: usize,
+    /// Qualified name
+    name: String,
+    /// Name within its file, e.g. `Outer.Inner`
+    local: String,
+    decl: &'a TypeDecl,
+}
+
+/// Qualified name of a supertype as known to a file.
+fn resolve(
+    known: &BTreeMap<String, usize>,
+    file: &JavaFile,
+    declaration: &str,
+    name: &str,
+) -> Option<String> {
+    let head = name.split('.').next().unwrap_or_default();
+    let mut candidates = Vec::new();
+
+    // Nested types of the enclosing types come first, as in Java
+    let mut scope = Some(declaration);
+
+    while let Some(outer) = scope.filter(|scope| known.contains_key(*scope)) {
+        candidates.push(format!("{}.{}", outer, name));
+        scope = outer.rsplit_once('.').map(|(outer, _)| outer);
+    }
+
+    for import in &file.imports {
+        if !import.wildcard && import.path.rsplit('.').next() == Some(head) {
+            candidates.push(format!("{}{}", import.path, &name[head.len()..]));
+        }
+    }
+
+    candidates.push(qualify(file, name));
+
+    for import in &file.imports {
+        if let Some(package) = import.path.strip_suffix(".*") {
+            candidates.push(format!("{}.{}", package, name));
+        }
+    }
+
+    candidates.push(name.to_owned());
+    candidates.into_iter().find(|name| known.contains_key(name))
+}
+
+/// Renames by relative path of a file.
+type ByFile = BTreeMap<PathBuf, Vec<Rename>>;
+
+/// PHP names of the overloaded methods and constructors each file declares,
+/// consistent across the types related by inheritance and clear of the names
+/// of their other methods, and of the overloaded methods of the types of each
+/// file and of each type by qualified name.
+fn renames<'a>(
+    files: &'a [(&Path, JavaFile)],
+    strategy: Strategy,
+) -> (ByFile, ByFile, BTreeMap<String, Vec<Rename>>) {
+    let mut declarations = Vec::new();
+
+    for (i, (_, java)) in files.iter().enumerate() {
+        let mut stack = java
+            .types
+            .iter()
+            .map(|decl| (qualify(java, &decl.name), decl.name.clone(), decl))
+            .collect::<Vec<_>>();
+
+        while let Some((name, local, decl)) = stack.pop() {
+            for member in &decl.members {
+                if let MemberKind::Type(nested) = &member.kind {
+                    stack.push((
+                        format!("{}.{}", name, nested.name),
+                        format!("{}.{}", local, nested.name),
+                        nested,
+                    ));
+                }
+            }
+
+            declarations.push(Declaration {
+                file: i,
+                name,
+                local,
+                decl,
+            });
+        }
+    }
+
+    let known = declarations
+        .iter()
+        .enumerate()
+        .map(|(i, declaration)| (declaration.name.clone(), i))
+        .collect::<BTreeMap<_, _>>();
+
+    // Types related by inheritance must agree on the names of their methods
+    let mut family = (0..declarations.len()).collect::<Vec<_>>();
+
+    fn root(family: &mut [usize], mut i: usize) -> usize {
+        while family[i] != i {
+            family[i] = family[family[i]];
+            i = family[i];
+        }
+
+        i
+    }
+
+    for (i, declaration) in declarations.iter().enumerate() {
+        let java = &files[declaration.file].1;
+        let decl = declaration.decl;
+
+        for name in decl.extends.iter().chain(&decl.implements) {
+            if let Some(supertype) = resolve(&known, java, &declaration.name, name) {
+                let (a, b) = (root(&mut family, i), root(&mut family, known[&supertype]));
+                family[a] = b;
+            }
+        }
+    }
+
+    let mut overloads = BTreeMap::<(usize, &str), BTreeSet<&[String]>>::new();
+
+    for (i, declaration) in declarations.iter().enumerate() {
+        let root = root(&mut family, i);
+
+        for member in &declaration.decl.members {
+            if let MemberKind::Method(method) = &member.kind {
+                overloads
+                    .entry((root, &method.name))
+                    .or_default()
+                    .insert(&method.params);
+            }
+        }
+    }
+
+    // Names the overloads may not be renamed to, lowercase as PHP method names
+    // are case-insensitive, starting with those of the other methods
+    let mut taken = BTreeMap::<usize, BTreeSet<String>>::new();
+
+    for ((root, name), params) in &overloads {
+        // Dispatchers take the names of the overloaded methods
+        if params.len() == 1 || strategy == Strategy::Dispatch {
+            taken
+                .entry(*root)
+                .or_default()
+                .insert(name.to_ascii_lowercase());
+        }
+    }
+
+    let mut chosen = BTreeMap::<(usize, Option<&str>, &[String]), String>::new();
+
+    let mut rename = |root: usize, local: &str, method: Option<&'a str>, params: &'a [String]| {
+        let mut rename = Rename::new(local, method, params, strategy);
+
+        rename.name = chosen
+            .entry((root, method, params))
+            .or_insert_with(|| {
+                let taken = taken.entry(root).or_default();
+                let mut name = rename.name.clone();
+
+                for n in 2.. {
+                    if taken.insert(name.to_ascii_lowercase()) {
+                        break;
+                    }
+
+                    name = format!("{}{}", rename.name, n);
+                }
+
+                name
+            })
+            .clone();
+
+        rename
+    };
+
+    let mut renames = BTreeMap::<PathBuf, Vec<Rename>>::new();
+    let mut methods = BTreeMap::<usize, Vec<Rename>>::new();
+
+    for (i, declaration) in declarations.iter().enumerate() {
+        let root = root(&mut family, i);
+        let mut constructors = Vec::new();
+        let file = renames
+            .entry(files[declaration.file].0.to_owned())
+            .or_default();
+
+        for member in &declaration.decl.members {
+            match &member.kind {
+                MemberKind::Method(method)
+                    if overloads[&(root, method.name.as_str())].len() > 1 =>
+                {
+                    let rename =
+                        rename(root, &declaration.local, Some(&method.name), &method.params);
+
+                    methods.entry(root).or_default().push(rename.clone());
+                    file.push(rename);
+                }
+                MemberKind::Constructor(constructor) => constructors.push(&constructor.params),
+                _ => {}
+            }
+        }
+
+        if constructors.len() > 1 {
+            for params in constructors {
+                file.push(rename(root, &declaration.local, None, params));
+            }
+        }
+    }
+
+    let mut callable = BTreeMap::<PathBuf, Vec<Rename>>::new();
+    let mut by_type = BTreeMap::<String, Vec<Rename>>::new();
+
+    for (i, declaration) in declarations.iter().enumerate() {
+        let file = callable
+            .entry(files[declaration.file].0.to_owned())
+            .or_default();
+
+        let ty = by_type.entry(declaration.name.clone()).or_default();
+
+        for rename in methods.get(&root(&mut family, i)).into_iter().flatten() {
+            for renames in [&mut *file, &mut *ty] {
+                if !renames.contains(rename) {
+                    renames.push(rename.clone());
+                }
+            }
+        }
+    }
+
+    renames.retain(|_, renames| !renames.is_empty());
+    callable.retain(|_, renames| !renames.is_empty());
+    by_type.retain(|_, renames| !renames.is_empty());
+    (renames, callable, by_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(files: &[(&str, &str)]) -> Index {
+        Index::new(
+            files
+                .iter()
+                .map(|(path, source)| (Path::new(*path), *source)),
+            Strategy::Suffix,
+        )
+    }
+
+    fn names(renames: &[Rename]) -> Vec<&str> {
+        renames.iter().map(|rename| rename.name.as_str()).collect()
+    }
+
+    const CUSTOMER: &str = "package a;
+
+public class Customer {
+    private int id;
+    public String name;
+
+    public Customer(String name) {
+        this.name = name;
+    }
+
+    private void forget() {}
+
+    public String getName() {
+        return name;
+    }
+
+    public static class Address {}
+}
+";
+
+    #[test]
+    fn related_types() {
+        let index = index(&[
+            ("a/Customer.java", CUSTOMER),
+            ("a/Order.java", "package a; class Order {}"),
+            ("b/Invoice.java", "package b; class Invoice {}"),
+            ("c/Broken.java", "package c; class Broken {"),
+        ]);
+
+        let related = index.related(
+            "package b; import a.Customer.Address; import a.*; \
+            class Invoice { Customer customer; Order order; Broken broken; }",
+        );
+
+        assert_eq!(
+            related,
+            [
+                (
+                    Path::new("a/Customer.java"),
+                    "public class Customer {\n    public String name;\n    \
+                    public Customer(String name);\n    public String getName();\n    \
+                    public static class Address {\n    }\n}\n"
+                ),
+                (Path::new("a/Order.java"), "class Order {\n}\n"),
+            ]
+        );
+
+        assert_eq!(
+            index.related("package b; class Invoice { Invoice next; }"),
+            []
+        );
+    }
+
+    #[test]
+    fn dependencies() {
+        let index = index(&[
+            ("a/Customer.java", CUSTOMER),
+            ("a/Entity.java", "package a; abstract class Entity {}"),
+            (
+                "a/Named.java",
+                "package a; interface Named { interface Nested {} }",
+            ),
+            ("b/Invoice.java", "package b; class Invoice {}"),
+            ("b/Order.java", "package b; class Order {}"),
+        ]);
+
+        let dependencies = index.dependencies(
+            "package b; import a.Customer.Address; \
+            class Invoice extends a.Entity implements a.Named.Nested { \
+            class Line extends Order {} Customer customer; }",
+        );
+
+        assert_eq!(
+            dependencies.into_iter().collect::<Vec<_>>(),
+            [
+                Path::new("a/Customer.java"),
+                Path::new("a/Entity.java"),
+                Path::new("a/Named.java"),
+                Path::new("b/Order.java"),
+            ]
+        );
+    }
+
+    #[test]
+    fn package_types() {
+        let index = index(&[
+            ("a/Customer.java", CUSTOMER),
+            ("a/b/Order.java", "package a.b; class Order {}"),
+            ("Main.java", "class Main {} class Helper {}"),
+        ]);
+
+        assert_eq!(index.package_types(Some("a")), ["Customer"]);
+        assert_eq!(index.package_types(None), ["Helper", "Main"]);
+        assert!(index.package_types(Some("c")).is_empty());
+    }
+
+    #[test]
+    fn methods_by_qualified_name() {
+        let index = index(&[
+            (
+                "a/Foo.java",
+                "package a; class Foo { void add(int x) {} void add(String s) {} }",
+            ),
+            ("b/Foo.java", "package b; class Foo { void add(int x) {} }"),
+            (
+                "c/Foo.java",
+                "package c; class Foo { void put(int x) {} void put(long x) {} }",
+            ),
+        ]);
+
+        let methods = index.methods("package a; class Bar {}");
+        assert_eq!(methods("Foo").map(names), Some(vec!["addInt", "addString"]));
+        assert_eq!(methods("Baz").map(names), Some(vec![]));
+
+        let methods = index.methods("package d; import b.Foo; import c.*; class Bar {}");
+        assert_eq!(methods("Foo").map(names), Some(vec![]));
+
+        let methods = index.methods("package d; import c.*; class Bar {}");
+        assert_eq!(methods("Foo").map(names), Some(vec!["putInt", "putLong"]));
+
+        let methods = index.methods("package d; class Bar {}");
+        assert_eq!(methods("Foo"), None);
+    }
+
+    #[test]
+    fn renames_clear_of_other_methods() {
+        let index = index(&[(
+            "Invoice.java",
+            "class Invoice {
+                void add(int x) {}
+                void add(java.util.Date d) {}
+                void add(java.sql.Date d) {}
+                void addDate2(String s) {}
+                void addint(long x) {}
+            }",
+        )]);
+
+        assert_eq!(
+            names(index.renames(Path::new("Invoice.java"))),
+            ["addInt2", "addDate", "addDate3"]
+        );
+    }
+
+    #[test]
+    fn renames_across_inheritance() {
+        let index = index(&[
+            (
+                "Shape.java",
+                "abstract class Shape { abstract void scale(int f); void resize() {} }",
+            ),
+            (
+                "Circle.java",
+                "class Circle extends Shape { void scale(int f) {} void scale(double f) {} \
+                Circle() {} Circle(int r) {} }",
+            ),
+        ]);
+
+        assert_eq!(names(index.renames(Path::new("Shape.java"))), ["scaleInt"]);
+        assert_eq!(
+            names(index.renames(Path::new("Circle.java"))),
+            ["scaleInt", "scaleDouble", "constructNoArgs", "constructInt"]
+        );
+        assert_eq!(
+            names(index.callable(Path::new("Shape.java"))),
+            ["scaleInt", "scaleInt", "scaleDouble"]
+        );
+    }
+}
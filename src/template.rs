@@ -0,0 +1,380 @@
+use crate::prompt;
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Variables a template can refer to.
+pub const VARIABLES: &[&str] = &[
+    "java",
+    "path",
+    "package",
+    "namespace",
+    "php_version",
+    "related",
+    "glossary",
+];
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Variable(String),
+    If {
+        variable: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// An `{{#if}}` section being parsed.
+struct Section {
+    variable: String,
+    then: Vec<Node>,
+    otherwise: Option<Vec<Node>>,
+}
+
+/// Where the nodes being parsed go.
+fn current<'a>(root: &'a mut Vec<Node>, sections: &'a mut [Section]) -> &'a mut Vec<Node> {
+    match sections.last_mut() {
+        Some(Section {
+            otherwise: Some(otherwise),
+            ..
+        }) => otherwise,
+        Some(section) => &mut section.then,
+        None => root,
+    }
+}
+
+/// A prompt with `{{variable}}` placeholders and `{{#if variable}}` sections,
+/// with an optional `{{else}}`, kept when the variable is not empty.
+#[derive(Debug, Clone)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut root = Vec::new();
+        let mut sections = Vec::<Section>::new();
+        let mut rest = text;
+
+        let line = |rest: &str| text[..text.len() - rest.len()].matches('\n').count() + 1;
+
+        let known = |variable: &str, line: usize| match VARIABLES.contains(&variable) {
+            true => Ok(variable.to_owned()),
+            false => Err(format!(
+                "Line {}: unknown variable `{}`, expected one of {}",
+                line,
+                variable,
+                VARIABLES.join(", ")
+            )),
+        };
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                current(&mut root, &mut sections).push(Node::Text(rest[..start].to_owned()));
+            }
+
+            rest = &rest[start..];
+            let at = line(rest);
+
+            let end = rest
+                .find("}}")
+                .ok_or_else(|| format!("Line {}: unclosed `{{{{`", at))?;
+
+            let tag = rest[2..end].trim();
+            rest = &rest[end + 2..];
+
+            if let Some(variable) = tag.strip_prefix("#if ") {
+                sections.push(Section {
+                    variable: known(variable.trim(), at)?,
+                    then: Vec::new(),
+                    otherwise: None,
+                });
+            } else if tag == "else" {
+                match sections.last_mut() {
+                    Some(section) if section.otherwise.is_none() => {
+                        section.otherwise = Some(Vec::new())
+                    }
+                    _ => return Err(format!("Line {}: unexpected `{{{{else}}}}`", at)),
+                }
+            } else if tag == "/if" {
+                let section = sections
+                    .pop()
+                    .ok_or_else(|| format!("Line {}: unexpected `{{{{/if}}}}`", at))?;
+
+                current(&mut root, &mut sections).push(Node::If {
+                    variable: section.variable,
+                    then: section.then,
+                    otherwise: section.otherwise.unwrap_or_default(),
+                });
+            } else {
+                let variable = known(tag, at)?;
+                current(&mut root, &mut sections).push(Node::Variable(variable));
+            }
+        }
+
+        if let Some(section) = sections.last() {
+            return Err(format!(
+                "Unclosed `{{{{#if {}}}}}`, expected `{{{{/if}}}}`",
+                section.variable
+            ));
+        }
+
+        if !rest.is_empty() {
+            current(&mut root, &mut sections).push(Node::Text(rest.to_owned()));
+        }
+
+        Ok(Self { nodes: root })
+    }
+}
+
+impl Template {
+    /// Whether the template refers to a variable, in a section or not.
+    fn refers_to(&self, variable: &str) -> bool {
+        fn refers_to(nodes: &[Node], variable: &str) -> bool {
+            nodes.iter().any(|node| match node {
+                Node::Text(_) => false,
+                Node::Variable(name) => name == variable,
+                Node::If {
+                    then, otherwise, ..
+                } => refers_to(then, variable) || refers_to(otherwise, variable),
+            })
+        }
+
+        refers_to(&self.nodes, variable)
+    }
+
+    /// The template with its variables replaced, missing ones being empty.
+    pub fn render(&self, variables: &BTreeMap<&str, String>) -> String {
+        fn render(nodes: &[Node], variables: &BTreeMap<&str, String>, output: &mut String) {
+            for node in nodes {
+                match node {
+                    Node::Text(text) => output.push_str(text),
+                    Node::Variable(variable) => {
+                        output.push_str(variables.get(variable.as_str()).map_or("", String::as_str))
+                    }
+                    Node::If {
+                        variable,
+                        then,
+                        otherwise,
+                    } => match variables
+                        .get(variable.as_str())
+                        .is_some_and(|v| !v.is_empty())
+                    {
+                        true => render(then, variables, output),
+                        false => render(otherwise, variables, output),
+                    },
+                }
+            }
+        }
+
+        let mut output = String::new();
+        render(&self.nodes, variables, &mut output);
+        output
+    }
+}
+
+/// Template for the files under a directory relative to the source, e.g.
+/// `legacy=legacy.txt`, or for all files without a directory.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub dir: PathBuf,
+    pub file: PathBuf,
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (dir, file) = s.split_once('=').unwrap_or(("", s));
+
+        if file.trim().is_empty() {
+            return Err(format!("Expected [DIR=]FILE, found `{}`", s));
+        }
+
+        Ok(Self {
+            dir: PathBuf::from(dir.trim()),
+            file: PathBuf::from(file.trim()),
+        })
+    }
+}
+
+/// The templates the prompts of the files are built from.
+#[derive(Debug, Clone)]
+pub struct Templates {
+    default: Template,
+    /// Templates by directory, relative to the source
+    dirs: Vec<(PathBuf, Template)>,
+}
+
+impl Templates {
+    pub fn load(rules: &[Rule]) -> Result<Self> {
+        let mut templates = Self {
+            default: prompt::TEMPLATE.parse().map_err(|e: String| eyre!(e))?,
+            dirs: Vec::new(),
+        };
+
+        for rule in rules {
+            let template = fs::read_to_string(&rule.file)
+                .map_err(|e| eyre!(e))
+                .and_then(|text| text.parse::<Template>().map_err(|e| eyre!(e)))
+                .and_then(|template| match template.refers_to("java") {
+                    true => Ok(template),
+                    false => Err(eyre!(
+                        "The template must include the code as `{{{{java}}}}`"
+                    )),
+                })
+                .wrap_err_with(|| eyre!("{}", rule.file.display()))?;
+
+            templates.dirs.push((rule.dir.clone(), template));
+        }
+
+        Ok(templates)
+    }
+
+    /// The template of the deepest directory containing a file, relative to
+    /// the source.
+    pub fn get(&self, path: &Path) -> &Template {
+        self.dirs
+            .iter()
+            .filter(|(dir, _)| path.starts_with(dir))
+            .max_by_key(|(dir, _)| dir.components().count())
+            .map_or(&self.default, |(_, template)| template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, variables: &[(&'static str, &str)]) -> String {
+        let variables = variables
+            .iter()
+            .map(|&(name, value)| (name, value.to_owned()))
+            .collect();
+
+        template.parse::<Template>().unwrap().render(&variables)
+    }
+
+    #[test]
+    fn default_template() {
+        assert!(prompt::TEMPLATE.parse::<Template>().is_ok());
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(
+            render(
+                "Convert {{path}}:\n{{ java }}{{glossary}}",
+                &[("path", "A.java"), ("java", "class A {}")]
+            ),
+            "Convert A.java:\nclass A {}"
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let template = "{{#if related}}Related: {{related}}{{else}}None{{/if}}.\
+            {{#if glossary}}{{#if namespace}}{{namespace}}{{/if}}{{/if}}";
+
+        assert_eq!(render(template, &[("related", "B")]), "Related: B.");
+        assert_eq!(render(template, &[("related", "")]), "None.");
+        assert_eq!(
+            render(template, &[("glossary", "x"), ("namespace", "App")]),
+            "None.App"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        for (template, error) in [
+            (
+                "Hello\n{{name}}",
+                "Line 2: unknown variable `name`, expected one of java, path, package, \
+                namespace, php_version, related, glossary",
+            ),
+            (
+                "{{#if nothing}}{{/if}}",
+                "Line 1: unknown variable `nothing`, expected one of java, path, package, \
+                namespace, php_version, related, glossary",
+            ),
+            (
+                "{{#if java}}\n{{java}}",
+                "Unclosed `{{#if java}}`, expected `{{/if}}`",
+            ),
+            (
+                "{{#if java}}{{#if path}}{{/if}}",
+                "Unclosed `{{#if java}}`, expected `{{/if}}`",
+            ),
+            ("\n\n{{java", "Line 3: unclosed `{{`"),
+            ("{{else}}", "Line 1: unexpected `{{else}}`"),
+            (
+                "{{#if java}}{{else}}{{else}}{{/if}}",
+                "Line 1: unexpected `{{else}}`",
+            ),
+            ("{{java}}\n{{/if}}", "Line 2: unexpected `{{/if}}`"),
+        ] {
+            assert_eq!(
+                template.parse::<Template>().map(|_| ()),
+                Err(error.to_owned()),
+                "{:?}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn load() {
+        let dir =
+            std::env::temp_dir().join(format!("java-to-php-templates-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let rules = ["legacy", "legacy/batch", "other"].map(|name| Rule {
+            dir: PathBuf::from(name),
+            file: dir.join(name.replace('/', "-")),
+        });
+
+        fs::write(&rules[0].file, "Legacy code:\n{{java}}").unwrap();
+        fs::write(&rules[1].file, "{{#if java}}Batch job:\n{{java}}{{/if}}").unwrap();
+        fs::write(&rules[2].file, "Convert {{path}}").unwrap();
+
+        let templates = Templates::load(&rules[..2]).unwrap();
+        let error = Templates::load(&rules).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let variables = BTreeMap::from([("java", "class A {}".to_owned())]);
+        let render = |path: &str| templates.get(Path::new(path)).render(&variables);
+
+        assert_eq!(render("legacy/A.java"), "Legacy code:\nclass A {}");
+        assert_eq!(render("legacy/batch/A.java"), "Batch job:\nclass A {}");
+        assert_eq!(render("A.java"), "Java:\nclass A {}");
+        assert_eq!(
+            format!("{:#}", error),
+            format!(
+                "{}: The template must include the code as `{{{{java}}}}`",
+                rules[2].file.display()
+            )
+        );
+    }
+
+    #[test]
+    fn rules() {
+        let rule = "legacy = prompts/legacy.txt".parse::<Rule>().unwrap();
+        assert_eq!(rule.dir, Path::new("legacy"));
+        assert_eq!(rule.file, Path::new("prompts/legacy.txt"));
+
+        let rule = "prompt.txt".parse::<Rule>().unwrap();
+        assert_eq!(rule.dir, Path::new(""));
+
+        assert!("legacy=".parse::<Rule>().is_err());
+    }
+}
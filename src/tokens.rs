@@ -0,0 +1,46 @@
+use crate::backend::Prompt;
+use tiktoken_rs::{
+    cl100k_base_singleton, p50k_base_singleton, p50k_edit_singleton, r50k_base_singleton,
+    tokenizer::{get_tokenizer, Tokenizer},
+};
+
+pub use tiktoken_rs::model::get_context_size;
+
+pub fn count(model: &str, text: &str) -> usize {
+    let bpe = match get_tokenizer(model) {
+        Some(Tokenizer::P50kBase) => p50k_base_singleton(),
+        Some(Tokenizer::P50kEdit) => p50k_edit_singleton(),
+        Some(Tokenizer::R50kBase | Tokenizer::Gpt2) => r50k_base_singleton(),
+        // Models unknown to tiktoken are approximated with the current OpenAI tokenizer
+        Some(Tokenizer::Cl100kBase) | None => cl100k_base_singleton(),
+    };
+
+    let count = bpe.lock().encode_with_special_tokens(text).len();
+    count
+}
+
+/// Approximates the chat format overhead the same way tiktoken does.
+pub fn count_prompt(model: &str, prompt: &Prompt) -> usize {
+    prompt
+        .chat_messages()
+        .iter()
+        .map(|message| 4 + count(model, &message.content))
+        .sum::<usize>()
+        + 3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts() {
+        assert_eq!(count("gpt-4", "Hello world"), 2);
+        assert_eq!(count("local-model", "Hello world"), 2);
+        assert_eq!(count("gpt-4", ""), 0);
+
+        // Three tokens to prime the reply and four per message
+        let prompt = Prompt::new("Hello world").user("Hello world");
+        assert_eq!(count_prompt("gpt-4", &prompt), 3 + 2 * (4 + 2));
+    }
+}
@@ -0,0 +1,567 @@
+use serde_json::{Map, Number, Value};
+use std::fmt::Display;
+
+/// Parses the subset of TOML configuration files need: tables, arrays of
+/// tables, dotted keys, single-line strings, integers, floats, booleans,
+/// arrays and inline tables.
+pub fn parse(text: &str) -> Result<Map<String, Value>, String> {
+    Parser { text, position: 0 }.document()
+}
+
+/// The table at a dotted key, the last one for arrays of tables.
+fn descend<'a>(
+    mut table: &'a mut Map<String, Value>,
+    key: &[String],
+) -> Result<&'a mut Map<String, Value>, String> {
+    for segment in key {
+        let value = table
+            .entry(segment.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+
+        table = match value {
+            Value::Object(table) => table,
+            Value::Array(items) => match items.last_mut() {
+                Some(Value::Object(table)) => table,
+                _ => return Err(format!("`{}` is not a table", segment)),
+            },
+            _ => return Err(format!("`{}` is not a table", segment)),
+        };
+    }
+
+    Ok(table)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn error(&self, message: impl Display) -> String {
+        let line = self.text[..self.position].matches('\n').count() + 1;
+        format!("Line {}: {}", line, message)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+
+        if found {
+            self.position += c.len_utf8();
+        }
+
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => Err(self.error(format!("expected `{}`", c))),
+        }
+    }
+
+    /// Skips spaces and comments up to the end of the line.
+    fn blank(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t') => self.position += 1,
+                Some('#') => self.position += self.rest().find('\n').unwrap_or(self.rest().len()),
+                _ => break,
+            }
+        }
+    }
+
+    /// Skips spaces, comments and line breaks.
+    fn whitespace(&mut self) {
+        loop {
+            self.blank();
+
+            if self.rest().starts_with("\r\n") {
+                self.position += 2;
+            } else if !self.eat('\n') {
+                break;
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.blank();
+        self.eat('\r');
+
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(_) => Err(self.error("expected the end of the line")),
+        }
+    }
+
+    fn document(&mut self) -> Result<Map<String, Value>, String> {
+        let mut root = Map::new();
+        let mut table = Vec::new();
+
+        loop {
+            self.whitespace();
+
+            match self.peek() {
+                None => return Ok(root),
+                Some('[') => {
+                    self.position += 1;
+                    let array = self.eat('[');
+                    let key = self.key()?;
+                    self.expect(']')?;
+
+                    if array {
+                        self.expect(']')?;
+                    }
+
+                    let (last, parents) = key.split_last().unwrap_or_else(|| unreachable!());
+                    let parent = descend(&mut root, parents).map_err(|e| self.error(e))?;
+
+                    let value = parent.entry(last.clone()).or_insert_with(|| match array {
+                        true => Value::Array(Vec::new()),
+                        false => Value::Object(Map::new()),
+                    });
+
+                    match (value, array) {
+                        (Value::Array(items), true) => items.push(Value::Object(Map::new())),
+                        (Value::Object(_), false) => {}
+                        _ => {
+                            return Err(self.error(format!(
+                                "`{}` is already defined as something else",
+                                key.join(".")
+                            )))
+                        }
+                    }
+
+                    self.end_of_line()?;
+                    table = key;
+                }
+                Some(_) => {
+                    let key = self.key()?;
+                    self.expect('=')?;
+                    self.blank();
+                    let value = self.value()?;
+
+                    let table = descend(&mut root, &table).map_err(|e| self.error(e))?;
+                    self.insert(table, &key, value)?;
+                    self.end_of_line()?;
+                }
+            }
+        }
+    }
+
+    fn insert(
+        &self,
+        table: &mut Map<String, Value>,
+        key: &[String],
+        value: Value,
+    ) -> Result<(), String> {
+        let (last, parents) = key.split_last().unwrap_or_else(|| unreachable!());
+        let parent = descend(table, parents).map_err(|e| self.error(e))?;
+
+        match parent.contains_key(last) {
+            true => Err(self.error(format!("`{}` is defined twice", key.join(".")))),
+            false => {
+                parent.insert(last.clone(), value);
+                Ok(())
+            }
+        }
+    }
+
+    fn key(&mut self) -> Result<Vec<String>, String> {
+        let mut key = Vec::new();
+
+        loop {
+            self.blank();
+
+            let segment = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let length = self
+                        .rest()
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                        .unwrap_or(self.rest().len());
+
+                    if length == 0 {
+                        return Err(self.error("expected a key"));
+                    }
+
+                    let segment = self.rest()[..length].to_owned();
+                    self.position += length;
+                    segment
+                }
+            };
+
+            key.push(segment);
+            self.blank();
+
+            if !self.eat('.') {
+                return Ok(key);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => {
+                self.position += 1;
+                let mut items = Vec::new();
+
+                loop {
+                    self.whitespace();
+
+                    if self.eat(']') {
+                        return Ok(Value::Array(items));
+                    }
+
+                    items.push(self.value()?);
+                    self.whitespace();
+
+                    if !self.eat(',') {
+                        self.expect(']')?;
+                        return Ok(Value::Array(items));
+                    }
+                }
+            }
+            Some('{') => {
+                self.position += 1;
+                let mut table = Map::new();
+                self.blank();
+
+                if self.eat('}') {
+                    return Ok(Value::Object(table));
+                }
+
+                loop {
+                    let key = self.key()?;
+                    self.expect('=')?;
+                    self.blank();
+                    let value = self.value()?;
+                    self.insert(&mut table, &key, value)?;
+                    self.blank();
+
+                    if self.eat('}') {
+                        return Ok(Value::Object(table));
+                    }
+
+                    self.expect(',')?;
+                }
+            }
+            _ => {
+                let length = self
+                    .rest()
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || "+-._".contains(c)))
+                    .unwrap_or(self.rest().len());
+
+                let word = &self.rest()[..length];
+                let number = word.replace('_', "");
+
+                let value = match word {
+                    "" => return Err(self.error("expected a value")),
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    _ => match number.parse::<i64>() {
+                        Ok(number) => Value::from(number),
+                        Err(_) => number
+                            .parse()
+                            .ok()
+                            .and_then(Number::from_f64)
+                            .map(Value::Number)
+                            .ok_or_else(|| self.error(format!("unexpected `{}`", word)))?,
+                    },
+                };
+
+                self.position += length;
+                Ok(value)
+            }
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut string = String::new();
+
+        loop {
+            let c = match self.peek() {
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(c) => c,
+            };
+
+            self.position += c.len_utf8();
+
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escape = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+
+                    self.position += escape.len_utf8();
+
+                    string.push(match escape {
+                        'b' => '\u{8}',
+                        't' => '\t',
+                        'n' => '\n',
+                        'f' => '\u{c}',
+                        'r' => '\r',
+                        '"' => '"',
+                        '\\' => '\\',
+                        'u' | 'U' => {
+                            let length = if escape == 'u' { 4 } else { 8 };
+                            let hex = self.rest().get(..length).unwrap_or_default();
+
+                            let c = u32::from_str_radix(hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| {
+                                    self.error(format!("invalid escape `\\{}{}`", escape, hex))
+                                })?;
+
+                            self.position += length;
+                            c
+                        }
+                        _ => return Err(self.error(format!("invalid escape `\\{}`", escape))),
+                    });
+                }
+                c => string.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.expect('\'')?;
+
+        let length = self
+            .rest()
+            .find(['\'', '\n'])
+            .filter(|&n| self.rest()[n..].starts_with('\''))
+            .ok_or_else(|| self.error("unterminated string"))?;
+
+        let string = self.rest()[..length].to_owned();
+        self.position += length + 1;
+        Ok(string)
+    }
+}
+
+fn key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+    match bare {
+        true => key.to_owned(),
+        false => inline(&Value::String(key.to_owned())),
+    }
+}
+
+/// Whether a value is left out, to keep unset options out of the output.
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Array(items) => items.is_empty(),
+        Value::Object(table) => table.is_empty(),
+        _ => false,
+    }
+}
+
+fn is_array_of_tables(value: &Value) -> bool {
+    matches!(value, Value::Array(items) if items.iter().all(Value::is_object))
+}
+
+fn inline(value: &Value) -> String {
+    match value {
+        Value::Array(items) => format!(
+            "[{}]",
+            items.iter().map(inline).collect::<Vec<_>>().join(", ")
+        ),
+        Value::Object(table) => format!(
+            "{{ {} }}",
+            table
+                .iter()
+                .filter(|(_, value)| !is_empty(value))
+                .map(|(name, value)| format!("{} = {}", key(name), inline(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        // JSON strings are valid TOML strings
+        value => value.to_string(),
+    }
+}
+
+fn write(output: &mut String, path: &[String], table: &Map<String, Value>) {
+    let entries = table
+        .iter()
+        .filter(|(_, value)| !is_empty(value))
+        .collect::<Vec<_>>();
+
+    for &(name, value) in &entries {
+        if !value.is_object() && !is_array_of_tables(value) {
+            output.push_str(&format!("{} = {}\n", key(name), inline(value)));
+        }
+    }
+
+    for (name, value) in entries {
+        let mut path = path.to_owned();
+        path.push(key(name));
+
+        match value {
+            Value::Object(table) => {
+                output.push_str(&format!("\n[{}]\n", path.join(".")));
+                write(output, &path, table);
+            }
+            Value::Array(items) if is_array_of_tables(value) => {
+                for item in items.iter().filter_map(Value::as_object) {
+                    output.push_str(&format!("\n[[{}]]\n", path.join(".")));
+                    write(output, &path, item);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Writes a table as TOML, leaving out nulls and empty arrays and tables.
+pub fn to_string(table: &Map<String, Value>) -> String {
+    let mut output = String::new();
+    write(&mut output, &[], table);
+    output.trim_start().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, Override};
+    use serde_json::json;
+
+    fn table(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(table) => table,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let table = table(json!({
+            "name": "quote \" backslash \\ tab \t newline \n é",
+            "count": 3,
+            "negative": -12,
+            "ratio": 0.5,
+            "enabled": false,
+            "list": [1, "two", [3.5], { "inline": true }],
+            "dotted.key": "quoted",
+            "nested": { "deeper": { "value": "x" }, "items": ["a"] },
+            "tables": [{ "paths": ["src/**"], "limit": 2 }, { "paths": [] }],
+        }));
+
+        let text = to_string(&table);
+        let parsed = parse(&text).unwrap_or_else(|e| panic!("{}\n{}", e, text));
+
+        // Empty arrays are left out
+        let mut expected = table;
+        expected["tables"][1] = json!({});
+
+        assert_eq!(parsed, expected, "{}", text);
+    }
+
+    #[test]
+    fn config_round_trip() {
+        let config: Config = serde_json::from_value(json!({
+            "backend": "compatible",
+            "base-url": "http://localhost:8080/v1",
+            "jobs": 4,
+            "strict-types": true,
+            "php-version": "8.0",
+            "include": ["**/*.java"],
+            "namespace-map": { "com.example": "App" },
+            "templates": { "legacy": "prompts/legacy.txt" },
+            "overrides": [
+                { "paths": ["legacy/**"], "php-version": "7.4", "max-repairs": 1 },
+                { "paths": ["tools/**"], "post-process": ["php -l {file}"] },
+            ],
+        }))
+        .unwrap();
+
+        let text = config.to_toml().unwrap();
+        let parsed = parse(&text).unwrap_or_else(|e| panic!("{}\n{}", e, text));
+        let reparsed: Config = serde_json::from_value(Value::Object(parsed)).unwrap();
+
+        assert_eq!(reparsed.to_toml().unwrap(), text);
+        assert_eq!(reparsed.jobs, Some(4));
+        assert_eq!(
+            reparsed.php_version.map(|v| v.to_string()).as_deref(),
+            Some("8.0")
+        );
+        assert_eq!(reparsed.namespace_map["com.example"], "App");
+
+        let [legacy, tools]: [Override; 2] = reparsed.overrides.try_into().unwrap();
+        assert_eq!(legacy.max_repairs, Some(1));
+        assert_eq!(tools.post_process, Some(vec!["php -l {file}".to_owned()]));
+    }
+
+    #[test]
+    fn syntax() {
+        let text = r#"
+# Comment
+a = 1 # Trailing comment
+"quoted key" = 'literal \n'
+b.c = "\u00e9"
+array = [
+    1,
+    2,
+]
+
+[[items]]
+name = "first"
+
+[[items]]
+name = "second"
+"#;
+
+        assert_eq!(
+            parse(text).unwrap(),
+            table(json!({
+                "a": 1,
+                "quoted key": "literal \\n",
+                "b": { "c": "é" },
+                "array": [1, 2],
+                "items": [{ "name": "first" }, { "name": "second" }],
+            }))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        for (text, error) in [
+            ("a = 1\nb = ", "Line 2: expected a value"),
+            ("a = 1\n\na = 2", "Line 3: `a` is defined twice"),
+            ("a = \"open\nb = 1", "Line 1: unterminated string"),
+            ("a = 1 2", "Line 1: expected the end of the line"),
+            ("a = [1,\n2\nb]", "Line 3: expected `]`"),
+            (
+                "a = 1\n[a]\n",
+                "Line 2: `a` is already defined as something else",
+            ),
+            ("a = 1\n[a.b]\n", "Line 2: `a` is not a table"),
+            ("\n\n= 1", "Line 3: expected a key"),
+            ("a = nope", "Line 1: unexpected `nope`"),
+            ("a = \"\\q\"", "Line 1: invalid escape `\\q`"),
+        ] {
+            assert_eq!(parse(text), Err(error.to_owned()), "{:?}", text);
+        }
+    }
+}
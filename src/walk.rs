@@ -0,0 +1,371 @@
+use crate::{
+    dependencies::BUILD_FILES,
+    java,
+    namespace::{self, Rule},
+};
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Where converted files are placed in the destination directory.
+pub enum Layout {
+    /// Same relative path as the source
+    Mirror,
+    /// Under `src/`, at the path of the namespace mapped from the package
+    Psr4(Vec<Rule>),
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    /// Path relative to the source directory
+    pub relative_path: PathBuf,
+    pub path: PathBuf,
+    /// Path of the PHP file in the destination directory
+    pub new_path: PathBuf,
+    /// Namespace the PHP file must declare
+    pub namespace: Option<String>,
+}
+
+impl Layout {
+    fn place(&self, path: &Path, relative_path: &Path, destination: &Path) -> Result<SourceFile> {
+        let (mut new_path, namespace) = match self {
+            Self::Mirror => (destination.join(relative_path), None),
+            Self::Psr4(rules) => match java::package(&fs::read_to_string(path)?) {
+                Ok(package) => {
+                    let namespace = namespace::namespace(rules, &package.unwrap_or_default());
+                    let mut new_path = destination.join("src");
+                    new_path.extend(namespace.split('\\'));
+                    new_path.push(path.file_name().ok_or_else(|| eyre!("Invalid file name"))?);
+                    (new_path, Some(namespace).filter(|n| !n.is_empty()))
+                }
+                // Left to fail the conversion, at the same path as in the source
+                Err(e) => {
+                    eprintln!(
+                        "{}: Cannot read the package, keeping the relative path: {}",
+                        path.display(),
+                        e
+                    );
+
+                    (destination.join(relative_path), None)
+                }
+            },
+        };
+
+        new_path.set_extension("php");
+
+        Ok(SourceFile {
+            relative_path: relative_path.to_owned(),
+            path: path.to_owned(),
+            new_path,
+            namespace,
+        })
+    }
+}
+
+/// Name of the files listing paths to leave out, in the syntax of `.gitignore`.
+pub const IGNORE_FILE_NAME: &str = ".javatophpignore";
+
+/// Which files of the source are walked.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Globs of the only Java files to convert, if any, relative to the source
+    pub include: Vec<String>,
+    /// Globs of the files to leave out, relative to the source
+    pub exclude: Vec<String>,
+    /// Walk hidden files and directories
+    pub hidden: bool,
+    /// Walk the files ignored by `.gitignore` and other Git excludes
+    pub no_ignore_vcs: bool,
+    pub follow_links: bool,
+}
+
+/// Walks the source without the excluded and ignored files. Included files
+/// are matched separately, as override globs would take precedence over the
+/// ignore files.
+fn builder(source: &Path, filter: &Filter) -> Result<WalkBuilder> {
+    let mut overrides = OverrideBuilder::new(source);
+
+    for glob in &filter.exclude {
+        overrides.add(&format!("!{}", glob))?;
+    }
+
+    let mut builder = WalkBuilder::new(source);
+
+    builder
+        .overrides(overrides.build()?)
+        .add_custom_ignore_filename(IGNORE_FILE_NAME)
+        .hidden(!filter.hidden)
+        .git_ignore(!filter.no_ignore_vcs)
+        .git_global(!filter.no_ignore_vcs)
+        .git_exclude(!filter.no_ignore_vcs)
+        .follow_links(filter.follow_links);
+
+    Ok(builder)
+}
+
+/// Finds the Maven and Gradle build files of a source directory.
+pub fn build_files(source: &Path, filter: &Filter) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    if !source.is_dir() {
+        return Ok(files);
+    }
+
+    for result in builder(source, filter)?.build() {
+        let path = result?.into_path();
+        let name = path.file_name().and_then(|name| name.to_str());
+
+        if path.is_file() && name.is_some_and(|name| BUILD_FILES.contains(&name)) {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+/// Finds the Java files of a source file or directory, without touching the
+/// destination.
+pub fn walk(
+    source: &Path,
+    destination: &Path,
+    layout: &Layout,
+    filter: &Filter,
+) -> Result<Vec<SourceFile>> {
+    let place = |path: &Path, relative_path: &Path| {
+        layout
+            .place(path, relative_path, destination)
+            .wrap_err_with(|| eyre!("{}", path.display()))
+    };
+
+    if source.is_file() {
+        let file_name = source
+            .file_name()
+            .ok_or_else(|| eyre!("Invalid file name"))?;
+
+        return Ok(vec![place(source, Path::new(file_name))?]);
+    }
+
+    if !source.is_dir() {
+        return Err(eyre!("{}: No such file or directory", source.display()));
+    }
+
+    let mut included = OverrideBuilder::new(source);
+
+    for glob in &filter.include {
+        included.add(glob)?;
+    }
+
+    let included = included.build()?;
+    let mut files = Vec::new();
+
+    for result in builder(source, filter)?
+        .filter_entry(|entry| {
+            let path = entry.path();
+
+            path.is_dir()
+                || path.is_file() && path.extension().map(|ext| ext == "java").unwrap_or(false)
+        })
+        .build()
+    {
+        let path = result?.into_path();
+
+        let relative_path = path.strip_prefix(source)?;
+
+        let is_included =
+            included.is_empty() || included.matched(relative_path, false).is_whitelist();
+
+        if path.is_file() && is_included {
+            files.push(place(&path, relative_path)?);
+        }
+    }
+
+    // Files of the same name in packages mapped to the same namespace
+    let mut destinations = BTreeMap::<&Path, &Path>::new();
+    let mut clashes = Vec::new();
+
+    for file in &files {
+        if let Some(other) = destinations.insert(&file.new_path, &file.relative_path) {
+            clashes.push(format!(
+                "{} and {} would both be converted to {}",
+                other.display(),
+                file.relative_path.display(),
+                file.new_path.display()
+            ));
+        }
+    }
+
+    if !clashes.is_empty() {
+        return Err(eyre!("{}", clashes.join("\n")));
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A source directory of files by relative path and content.
+    fn source(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("java-to-php-{}-{}", name, std::process::id()));
+
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        dir
+    }
+
+    fn psr4(rules: &[&str]) -> Layout {
+        Layout::Psr4(rules.iter().map(|rule| rule.parse().unwrap()).collect())
+    }
+
+    #[test]
+    fn psr4_paths() {
+        let dir = source(
+            "walk",
+            &[
+                (
+                    "com/acme/my_billing/Invoice.java",
+                    "package com.acme.my_billing; class Invoice {}",
+                ),
+                ("Main.java", "class Main {}"),
+                ("broken/Broken.java", "package 42; class Broken {}"),
+            ],
+        );
+
+        let mut files = walk(
+            &dir,
+            Path::new("out"),
+            &psr4(&["com.acme=Acme"]),
+            &Filter::default(),
+        )
+        .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+        let placed = files
+            .iter()
+            .map(|file| (file.new_path.to_str().unwrap(), file.namespace.as_deref()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            placed,
+            [
+                ("out/src/Main.php", None),
+                ("out/broken/Broken.php", None),
+                (
+                    "out/src/Acme/MyBilling/Invoice.php",
+                    Some("Acme\\MyBilling")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn same_destination() {
+        let dir = source(
+            "walk-clash",
+            &[
+                ("a/Invoice.java", "package com.a; class Invoice {}"),
+                ("b/Invoice.java", "package com.b; class Invoice {}"),
+                ("c/Invoice.java", "package com.c; class Invoice {}"),
+            ],
+        );
+
+        let layout = psr4(&["com.a=App", "com.b=App"]);
+        let error = walk(&dir, Path::new("out"), &layout, &Filter::default()).unwrap_err();
+        let files = walk(&dir, Path::new("out"), &Layout::Mirror, &Filter::default()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let error = error.to_string();
+        assert!(
+            error.ends_with(" would both be converted to out/src/App/Invoice.php"),
+            "{}",
+            error
+        );
+        assert!(error.contains("a/Invoice.java") && error.contains("b/Invoice.java"));
+        assert!(!error.contains('\n'));
+        assert_eq!(files.len(), 3);
+    }
+
+    fn relative_paths(dir: &Path, filter: &Filter) -> Vec<String> {
+        let mut paths = walk(dir, Path::new("out"), &Layout::Mirror, filter)
+            .unwrap()
+            .into_iter()
+            .map(|file| file.relative_path.display().to_string())
+            .collect::<Vec<_>>();
+
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn filters() {
+        let dir = source(
+            "walk-filters",
+            &[
+                ("A.java", ""),
+                ("README.md", ""),
+                ("pom.xml", "<project/>"),
+                ("core/B.java", ""),
+                ("core/generated/C.java", ""),
+                ("test/BTest.java", ""),
+                (".hidden/D.java", ""),
+                ("legacy/E.java", ""),
+                ("legacy/build.gradle", ""),
+                (IGNORE_FILE_NAME, "legacy/\n"),
+            ],
+        );
+
+        let all = relative_paths(&dir, &Filter::default());
+
+        let included = relative_paths(
+            &dir,
+            &Filter {
+                include: vec!["core/**".to_owned()],
+                exclude: vec!["generated".to_owned()],
+                ..Filter::default()
+            },
+        );
+
+        let excluded = relative_paths(
+            &dir,
+            &Filter {
+                exclude: vec!["test/".to_owned(), "A.java".to_owned()],
+                hidden: true,
+                ..Filter::default()
+            },
+        );
+
+        let build_files = build_files(&dir, &Filter::default()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            all,
+            [
+                "A.java",
+                "core/B.java",
+                "core/generated/C.java",
+                "test/BTest.java"
+            ]
+        );
+        assert_eq!(included, ["core/B.java"]);
+        assert_eq!(
+            excluded,
+            [".hidden/D.java", "core/B.java", "core/generated/C.java"]
+        );
+        assert_eq!(build_files, [dir.join("pom.xml")]);
+    }
+}